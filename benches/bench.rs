@@ -3,9 +3,34 @@
 extern crate test;
 extern crate wasm_game_of_life;
 
+use wasm_game_of_life::Universe;
+
+/// A 1024x1024 universe seeded with a fixed soup, run with `algorithm`.
+fn universe(algorithm: &str) -> Universe {
+    let mut universe = Universe::new();
+    universe.set_width(1024);
+    universe.set_height(1024);
+    universe.set_algorithm(algorithm).unwrap();
+    let cells: Vec<(u32, u32)> = (0..1024 * 1024)
+        .filter(|idx| (idx * 7 + idx / 1024 * 3) % 5 < 2)
+        .map(|idx| (idx / 1024, idx % 1024))
+        .collect();
+    universe.set_cells(&cells);
+    universe
+}
+
 #[bench]
 fn universe_ticks(b: &mut test::Bencher) {
-    let mut universe = wasm_game_of_life::Universe::new();
+    let mut universe = universe("generic");
+
+    b.iter(|| {
+        universe.tick();
+    });
+}
+
+#[bench]
+fn universe_ticks_bitpacked(b: &mut test::Bencher) {
+    let mut universe = universe("bitpacked");
 
     b.iter(|| {
         universe.tick();
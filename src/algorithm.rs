@@ -0,0 +1,71 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::rule::Rule;
+use crate::topology::Topology;
+use crate::{bitpacked, hashlife};
+
+/// How a universe is advanced from one generation to the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Compute every cell of every generation. Works with every rule and
+    /// topology.
+    #[default]
+    Generic,
+    /// Pack 64 cells into each word and count their neighbors with bitwise
+    /// adders. Only runs two-state totalistic rules on the eight nearest
+    /// neighbors, on a torus or a bounded plane.
+    BitPacked,
+    /// Memoize the future of repeated blocks of cells in a quadtree, which
+    /// makes it possible to jump far ahead in time. Only runs two-state
+    /// rules on the eight nearest neighbors, on an unbounded plane.
+    HashLife,
+}
+
+impl Algorithm {
+    /// Check that the algorithm can run `rule` on `topology`.
+    pub fn check(self, rule: &Rule, topology: Topology) -> Result<(), String> {
+        let (supported, topologies) = match self {
+            Algorithm::Generic => return Ok(()),
+            Algorithm::BitPacked => (
+                bitpacked::supports(rule),
+                matches!(topology, Topology::Torus | Topology::Plane),
+            ),
+            Algorithm::HashLife => (hashlife::supports(rule), topology == Topology::Unbounded),
+        };
+        if !supported {
+            return Err(format!("rule \"{}\" cannot run with {}", rule, self));
+        }
+        if !topologies {
+            return Err(format!("{} cannot run on a {}", self, topology));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Algorithm, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "generic" => Ok(Algorithm::Generic),
+            "bitpacked" => Ok(Algorithm::BitPacked),
+            "hashlife" => Ok(Algorithm::HashLife),
+            _ => Err(format!(
+                "unknown algorithm \"{}\", expected \"generic\", \"bitpacked\" or \"hashlife\"",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Algorithm::Generic => "generic",
+            Algorithm::BitPacked => "bitpacked",
+            Algorithm::HashLife => "hashlife",
+        };
+        write!(f, "{}", name)
+    }
+}
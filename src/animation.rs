@@ -0,0 +1,157 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use gif::{Encoder, Frame, Repeat};
+
+use crate::image::Palette;
+use crate::Cell;
+
+/// The frames of a run of a `width` by `height` universe, each holding its
+/// cells row by row.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Animation {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<Vec<Cell>>,
+}
+
+impl Animation {
+    pub fn new(width: u32, height: u32) -> Animation {
+        Animation {
+            width,
+            height,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, cells: &[Cell]) {
+        self.frames.push(cells.to_vec());
+    }
+
+    /// Cut every frame down to the smallest rectangle holding the cells that
+    /// are not dead in any frame, so the pattern does not jump around. An
+    /// animation without such cells is left as it is.
+    pub fn crop(&mut self) {
+        let width = self.width;
+        let occupied = self.frames.iter().flat_map(|cells| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|(_, &cell)| cell != Cell::DEAD)
+                .map(move |(idx, _)| (idx as u32 / width, idx as u32 % width))
+        });
+        let (mut top, mut left, mut bottom, mut right) = (u32::MAX, u32::MAX, 0, 0);
+        for (row, col) in occupied {
+            top = top.min(row);
+            left = left.min(col);
+            bottom = bottom.max(row);
+            right = right.max(col);
+        }
+        if top > bottom {
+            return;
+        }
+        for cells in &mut self.frames {
+            *cells = (top..=bottom)
+                .flat_map(|row| {
+                    let start = (row * width) as usize;
+                    cells[start + left as usize..=start + right as usize].to_vec()
+                })
+                .collect();
+        }
+        self.width = right - left + 1;
+        self.height = bottom - top + 1;
+    }
+
+    /// The animation as a looping GIF, with each cell drawn as a square of
+    /// `cell_px` pixels and each frame shown for `delay_ms` milliseconds,
+    /// rounded up to the hundredths of a second GIF counts in.
+    pub fn to_gif(&self, cell_px: u32, delay_ms: u32, palette: &Palette) -> Result<Vec<u8>, String> {
+        let too_large = || {
+            format!(
+                "a {} by {} animation is too large for a GIF with {} pixel cells",
+                self.width, self.height, cell_px
+            )
+        };
+        let pixels = |cells: u32| cells.checked_mul(cell_px).and_then(|pixels| u16::try_from(pixels).ok());
+        let width = pixels(self.width).ok_or_else(too_large)?;
+        let height = pixels(self.height).ok_or_else(too_large)?;
+        let delay = u16::try_from(delay_ms.div_ceil(10)).unwrap_or(u16::MAX);
+        let colors: Vec<u8> = palette.states.iter().flatten().cloned().collect();
+        let error = |error: gif::EncodingError| format!("cannot write GIF: {}", error);
+
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = Encoder::new(&mut bytes, width, height, &colors).map_err(error)?;
+            encoder.set_repeat(Repeat::Infinite).map_err(error)?;
+            for cells in &self.frames {
+                let mut pixels = Vec::with_capacity(width as usize * height as usize);
+                for row in cells.chunks(self.width as usize) {
+                    let line: Vec<u8> = row
+                        .iter()
+                        .flat_map(|cell| std::iter::repeat_n(cell.state(), cell_px as usize))
+                        .collect();
+                    for _ in 0..cell_px {
+                        pixels.extend_from_slice(&line);
+                    }
+                }
+                let frame = Frame {
+                    width,
+                    height,
+                    delay,
+                    buffer: Cow::Owned(pixels),
+                    ..Frame::default()
+                };
+                encoder.write_frame(&frame).map_err(error)?;
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+
+    #[test]
+    fn test_crop() {
+        let (o, x) = (Cell::DEAD, Cell::ALIVE);
+        let mut animation = Animation::new(4, 3);
+        animation.push(&[o, o, o, o, o, x, o, o, o, o, o, o]);
+        animation.push(&[o, o, o, o, o, o, o, o, o, o, x, o]);
+        animation.crop();
+        assert_eq!((animation.width, animation.height), (2, 2));
+        assert_eq!(animation.frames, vec![vec![x, o, o, o], vec![o, o, o, x]]);
+
+        let mut empty = Animation::new(4, 3);
+        empty.push(&[o; 12]);
+        empty.crop();
+        assert_eq!((empty.width, empty.height), (4, 3));
+    }
+
+    #[test]
+    fn test_to_gif() {
+        let (o, x) = (Cell::DEAD, Cell::ALIVE);
+        let mut animation = Animation::new(2, 1);
+        animation.push(&[x, o]);
+        animation.push(&[o, x]);
+        let palette = Palette::new(&Rule::conway(), &[]);
+        let bytes = animation.to_gif(3, 125, &palette).unwrap();
+        assert_eq!(&bytes[..6], b"GIF89a");
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(&bytes[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (6, 3));
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 13);
+            frames.push(frame.buffer.to_vec());
+        }
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], [1, 1, 1, 0, 0, 0].repeat(3));
+        assert_eq!(frames[1], [0, 0, 0, 1, 1, 1].repeat(3));
+
+        assert!(animation.to_gif(40_000, 10, &palette).is_err());
+    }
+}
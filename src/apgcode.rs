@@ -0,0 +1,227 @@
+use crate::object::Evolution;
+use crate::transform::Transform;
+
+/// The digits of a column of five cells, the top cell in the lowest bit.
+const DIGITS: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+/// The digits after `y` of a run of four to 39 empty columns.
+const RUN_DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// The height of the strips a pattern is cut into.
+const STRIP: i64 = 5;
+
+/// The extended Wechsler format of the live cells `cells`, as they are
+/// placed: the pattern is cut into strips five cells high, and each column
+/// of a strip is written as a base 32 digit. Runs of empty columns are
+/// shortened to `w` for two, `x` for three and `y` and a digit for four to
+/// 39, and strips are separated by `z`.
+pub fn wechsler(cells: &[(i64, i64)]) -> String {
+    if cells.is_empty() {
+        return String::from("0");
+    }
+    let top = cells.iter().map(|&(row, _)| row).min().unwrap();
+    let left = cells.iter().map(|&(_, col)| col).min().unwrap();
+    let height = cells.iter().map(|&(row, _)| row).max().unwrap() - top + 1;
+    let width = cells.iter().map(|&(_, col)| col).max().unwrap() - left + 1;
+
+    let strips = (height + STRIP - 1) / STRIP;
+    let mut columns = vec![vec![0u8; width as usize]; strips as usize];
+    for &(row, col) in cells {
+        let (row, col) = (row - top, col - left);
+        columns[(row / STRIP) as usize][col as usize] |= 1 << (row % STRIP);
+    }
+
+    let mut code = String::new();
+    for (strip, columns) in columns.iter().enumerate() {
+        if strip > 0 {
+            code.push('z');
+        }
+        let end = columns.iter().rposition(|&column| column != 0).map_or(0, |end| end + 1);
+        let mut zeros = 0;
+        let flush = |code: &mut String, zeros: &mut usize| {
+            while *zeros > 0 {
+                let run = (*zeros).min(39);
+                match run {
+                    1 => code.push('0'),
+                    2 => code.push('w'),
+                    3 => code.push('x'),
+                    run => {
+                        code.push('y');
+                        code.push(RUN_DIGITS[run - 4] as char);
+                    }
+                }
+                *zeros -= run;
+            }
+        };
+        for &column in &columns[..end] {
+            if column == 0 {
+                zeros += 1;
+            } else {
+                flush(&mut code, &mut zeros);
+                code.push(DIGITS[column as usize] as char);
+            }
+        }
+    }
+    code
+}
+
+/// The canonical extended Wechsler format of an object going through
+/// `phases`: the shortest of the codes of every phase in every orientation,
+/// ties going to the first in ASCII order.
+pub fn canonical(phases: &[Vec<(i64, i64)>]) -> String {
+    phases
+        .iter()
+        .flat_map(|phase| {
+            Transform::ALL.iter().map(move |transform| {
+                let cells: Vec<(i64, i64)> = phase.iter().map(|&cell| transform.linear(cell)).collect();
+                wechsler(&cells)
+            })
+        })
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_else(|| String::from("0"))
+}
+
+/// The apgcode of a two-state object: `xs` and its population for still
+/// lifes, `xp` and the period for oscillators or `xq` and the period for
+/// spaceships, then `_` and the object's canonical Wechsler format.
+pub fn encode(evolution: &Evolution) -> String {
+    let phases: Vec<Vec<(i64, i64)>> = evolution
+        .phases
+        .iter()
+        .map(|phase| {
+            phase
+                .iter()
+                .filter(|&&(_, _, cell)| cell.is_alive())
+                .map(|&(row, col, _)| (row, col))
+                .collect()
+        })
+        .collect();
+    let prefix = if evolution.dy != 0 || evolution.dx != 0 {
+        format!("xq{}", evolution.period)
+    } else if evolution.period == 1 {
+        format!("xs{}", phases[0].len())
+    } else {
+        format!("xp{}", evolution.period)
+    };
+    format!("{}_{}", prefix, canonical(&phases))
+}
+
+/// The live cells of an apgcode for a still life, oscillator or spaceship,
+/// relative to the top left corner of the pattern.
+pub fn decode(code: &str) -> Result<Vec<(i64, i64)>, String> {
+    let code = code.trim();
+    let invalid = |reason: &str| format!("invalid apgcode \"{}\": {}", code, reason);
+    let underscore = code
+        .find('_')
+        .ok_or_else(|| invalid("expected a prefix such as \"xs4_\""))?;
+    let (prefix, body) = (&code[..underscore], &code[underscore + 1..]);
+    let kind = prefix.get(..2);
+    let number = prefix.get(2..).unwrap_or("");
+    if !matches!(kind, Some("xs") | Some("xp") | Some("xq"))
+        || number.is_empty()
+        || !number.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return Err(invalid("expected a prefix of \"xs\", \"xp\" or \"xq\" and a number"));
+    }
+
+    let digit = |digits: &[u8], c: char| digits.iter().position(|&digit| digit as char == c);
+    let mut cells = Vec::new();
+    let (mut strip, mut col) = (0, 0);
+    // Not split at `z` first, which is also the digit of a run of 39.
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            'w' => col += 2,
+            'x' => col += 3,
+            'y' => {
+                let run = chars
+                    .next()
+                    .and_then(|c| digit(RUN_DIGITS, c))
+                    .ok_or_else(|| invalid("expected a digit after 'y'"))?;
+                col += run as i64 + 4;
+            }
+            'z' => {
+                strip += 1;
+                col = 0;
+            }
+            c => {
+                let column = digit(DIGITS, c).ok_or_else(|| invalid(&format!("unexpected character '{}'", c)))?;
+                for bit in 0..STRIP {
+                    if column >> bit & 1 != 0 {
+                        cells.push((strip * STRIP + bit, col));
+                    }
+                }
+                col += 1;
+            }
+        }
+    }
+    if cells.is_empty() {
+        return Err(invalid("the pattern is empty"));
+    }
+    cells.sort_unstable();
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::evolve;
+    use crate::rule::Rule;
+    use crate::Cell;
+
+    fn apgcode(cells: &[(i64, i64)]) -> String {
+        let cells: Vec<(i64, i64, Cell)> = cells.iter().map(|&(row, col)| (row, col, Cell::ALIVE)).collect();
+        encode(&evolve(&Rule::conway(), &cells, 100).unwrap())
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(apgcode(&[(0, 0), (0, 1), (1, 0), (1, 1)]), "xs4_33");
+        assert_eq!(apgcode(&[(5, 5), (5, 6), (5, 7)]), "xp2_7");
+        assert_eq!(apgcode(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]), "xq4_153");
+        // Beehive, in both of the orientations it has.
+        assert_eq!(apgcode(&[(0, 1), (0, 2), (1, 0), (1, 3), (2, 1), (2, 2)]), "xs6_696");
+        assert_eq!(apgcode(&[(0, 1), (1, 0), (1, 2), (2, 0), (2, 2), (3, 1)]), "xs6_696");
+        // Lightweight spaceship.
+        assert_eq!(
+            apgcode(&[(0, 1), (0, 4), (1, 0), (2, 0), (2, 4), (3, 0), (3, 1), (3, 2), (3, 3)]),
+            "xq4_6frc"
+        );
+        // Two blocks far apart have runs of empty columns and two strips.
+        assert_eq!(wechsler(&[(0, 0), (0, 1), (1, 0), (1, 1), (0, 12), (0, 13), (1, 12), (1, 13)]), "33y633");
+        assert_eq!(wechsler(&[(0, 0), (0, 1), (1, 0), (1, 1), (6, 0), (6, 1), (7, 0), (7, 1)]), "33z66");
+    }
+
+    #[test]
+    fn test_long_runs() {
+        // Runs of 36 and 39 empty columns, written with the digits after
+        // `v`, and a run of 40 split in two.
+        for &(gap, expected) in &[(36, "1yw1"), (39, "1yz1"), (40, "1yz01")] {
+            let cells = [(0, 0), (0, gap + 1)];
+            let code = wechsler(&cells);
+            assert_eq!(code, expected);
+            assert_eq!(decode(&format!("xs2_{}", code)).unwrap(), cells);
+        }
+        assert_eq!(wechsler(&decode("xs4_3yz3").unwrap()), "3yz3");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("xs4_33").unwrap(), vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+        assert_eq!(decode("xp2_7").unwrap(), vec![(0, 0), (1, 0), (2, 0)]);
+        assert_eq!(decode("xq4_153").unwrap(), vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 1)]);
+        for code in &["xs6_696", "xq4_6frc", "xs8_33y633", "xs8_33z66"] {
+            let body = &code[code.find('_').unwrap() + 1..];
+            assert_eq!(canonical(&[decode(code).unwrap()]), body);
+        }
+        assert_eq!(decode("xs2_0w3").unwrap(), vec![(0, 3), (1, 3)]);
+        assert_eq!(decode("xs4_3yz3").unwrap(), vec![(0, 0), (0, 40), (1, 0), (1, 40)]);
+
+        assert!(decode("33").is_err());
+        assert!(decode("xz4_33").is_err());
+        assert!(decode("xs_33").is_err());
+        assert!(decode("xs4_3!").is_err());
+        assert!(decode("xs4_3y").is_err());
+        assert!(decode("xs0_0").is_err());
+    }
+}
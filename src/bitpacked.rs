@@ -0,0 +1,262 @@
+use crate::rule::Rule;
+use crate::Cell;
+
+/// Whether a rule can be run by `BitGrid`: it must only have two states and
+/// only depend on the number of live cells among the eight nearest
+/// neighbors.
+pub fn supports(rule: &Rule) -> bool {
+    rule.states() == 2 && rule.totalistic().is_some()
+}
+
+/// A grid of cells packed 64 to a `u64`, one bit per cell, each row starting
+/// on a new word. Bits past the end of a row are always zero.
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub struct BitGrid {
+    width: u32,
+    height: u32,
+    /// The number of words in each row.
+    words: usize,
+    bits: Vec<u64>,
+}
+
+/// Add three bitplanes, returning the sum and carry bitplanes.
+fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let partial = a ^ b;
+    (partial ^ c, (a & b) | (partial & c))
+}
+
+fn half_add(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+impl BitGrid {
+    /// Pack the `width` by `height` cells of `cells`, stored row by row.
+    /// Dying cells are treated as dead.
+    pub fn from_cells(width: u32, height: u32, cells: &[Cell]) -> BitGrid {
+        let words = (width as usize).div_ceil(64);
+        let mut grid = BitGrid {
+            width,
+            height,
+            words,
+            bits: vec![0; words * height as usize],
+        };
+        for (idx, cell) in cells.iter().enumerate() {
+            if cell.is_alive() {
+                let (row, col) = (idx / width as usize, idx % width as usize);
+                grid.bits[row * words + col / 64] |= 1 << (col % 64);
+            }
+        }
+        grid
+    }
+
+    /// Unpack the grid into the byte per cell view of `cells`.
+    pub fn write_cells(&self, cells: &mut [Cell]) {
+        let width = self.width as usize;
+        for (row, cells) in cells.chunks_mut(width).enumerate() {
+            let words = &self.bits[row * self.words..(row + 1) * self.words];
+            for (col, cell) in cells.iter_mut().enumerate() {
+                let alive = words[col / 64] >> (col % 64) & 1 != 0;
+                *cell = if alive { Cell::ALIVE } else { Cell::DEAD };
+            }
+        }
+    }
+
+    pub fn get(&self, row: u32, col: u32) -> bool {
+        self.bits[row as usize * self.words + col as usize / 64] >> (col % 64) & 1 != 0
+    }
+
+    pub fn set(&mut self, row: u32, col: u32, alive: bool) {
+        let word = &mut self.bits[row as usize * self.words + col as usize / 64];
+        let bit = 1 << (col % 64);
+        if alive {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+    }
+
+    /// The number of live cells.
+    pub fn population(&self) -> u64 {
+        self.bits.iter().map(|word| word.count_ones() as u64).sum()
+    }
+
+    /// The births and deaths from `old` to this grid of the same size.
+    pub fn changes(&self, old: &BitGrid) -> (u32, u32) {
+        self.bits.iter().zip(&old.bits).fold((0, 0), |(births, deaths), (&new, &old)| {
+            (births + (new & !old).count_ones(), deaths + (old & !new).count_ones())
+        })
+    }
+
+    fn row(&self, row: usize) -> &[u64] {
+        &self.bits[row * self.words..(row + 1) * self.words]
+    }
+
+    /// The cells of a row shifted one column east and one column west, so
+    /// that bit `col` holds the cell west or east of `col`. With `wrap` the
+    /// row's ends are joined, otherwise cells beyond them are dead.
+    fn shift(&self, row: &[u64], word: usize, wrap: bool) -> (u64, u64) {
+        let last_col = self.width as usize - 1;
+        let bit = |col: usize| row[col / 64] >> (col % 64) & 1;
+
+        let mut west = row[word] << 1;
+        if word > 0 {
+            west |= row[word - 1] >> 63;
+        } else if wrap {
+            west |= bit(last_col);
+        }
+        let mut east = row[word] >> 1;
+        if word + 1 < self.words {
+            east |= row[word + 1] << 63;
+        } else if wrap {
+            east |= bit(0) << (last_col % 64);
+        }
+        (west, east)
+    }
+
+    /// Advance every cell one generation. `birth` and `survival` are
+    /// bitmasks of the neighbor counts causing births and survivals, bit `n`
+    /// being set for `n` live neighbors; see `Rule::totalistic`. With `wrap`
+    /// the grid is a torus, otherwise cells beyond its edges are dead.
+    ///
+    /// The eight neighbors of 64 cells at a time are summed with bitwise
+    /// full adders into four bitplanes holding each cell's count in binary.
+    /// Returns the number of births and deaths.
+    pub fn tick(&mut self, birth: u16, survival: u16, wrap: bool) -> (u32, u32) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        if width == 0 || height == 0 {
+            return (0, 0);
+        }
+        let last_mask = match width % 64 {
+            0 => !0,
+            bits => (1 << bits) - 1,
+        };
+        // For each count causing a birth or a survival, the bits of the
+        // count and whether it applies to dead and live cells.
+        let outcomes: Vec<(u32, u64, u64)> = (0..=8)
+            .filter(|&count| (birth | survival) >> count & 1 != 0)
+            .map(|count| {
+                let mask = |rule: u16| if rule >> count & 1 != 0 { !0 } else { 0 };
+                (count, mask(birth), mask(survival))
+            })
+            .collect();
+
+        let empty = vec![0; self.words];
+        let mut next = vec![0; self.bits.len()];
+        let (mut births, mut deaths) = (0, 0);
+        for row in 0..height {
+            let above = match row {
+                0 if wrap => self.row(height - 1),
+                0 => &empty,
+                row => self.row(row - 1),
+            };
+            let below = match row + 1 {
+                row if row < height => self.row(row),
+                _ if wrap => self.row(0),
+                _ => &empty,
+            };
+            let current = self.row(row);
+            for word in 0..self.words {
+                let (above_west, above_east) = self.shift(above, word, wrap);
+                let (west, east) = self.shift(current, word, wrap);
+                let (below_west, below_east) = self.shift(below, word, wrap);
+
+                let (above_ones, above_twos) = full_add(above_west, above[word], above_east);
+                let (below_ones, below_twos) = full_add(below_west, below[word], below_east);
+                let (side_ones, side_twos) = half_add(west, east);
+                let (ones, ones_carry) = full_add(above_ones, below_ones, side_ones);
+                let (twos, twos_carry) = full_add(above_twos, below_twos, side_twos);
+                let (twos, more_twos_carry) = half_add(twos, ones_carry);
+                let (fours, eights) = half_add(twos_carry, more_twos_carry);
+
+                let alive = current[word];
+                let mut cells = 0;
+                for &(count, born, survives) in &outcomes {
+                    let bitplane = |plane: u64, bit: u32| {
+                        if count >> bit & 1 != 0 {
+                            plane
+                        } else {
+                            !plane
+                        }
+                    };
+                    let matches = bitplane(ones, 0)
+                        & bitplane(twos, 1)
+                        & bitplane(fours, 2)
+                        & bitplane(eights, 3);
+                    cells |= matches & ((!alive & born) | (alive & survives));
+                }
+                if word + 1 == self.words {
+                    cells &= last_mask;
+                }
+                births += (cells & !alive).count_ones();
+                deaths += (alive & !cells).count_ones();
+                next[row * self.words + word] = cells;
+            }
+        }
+        self.bits = next;
+        (births, deaths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tick a `width` by `height` universe cell by cell, the slow way.
+    fn tick_cells(rule: &Rule, width: u32, height: u32, wrap: bool, cells: &[Cell]) -> Vec<Cell> {
+        let (width, height) = (width as i64, height as i64);
+        let alive = |row: i64, col: i64| {
+            if wrap {
+                cells[(row.rem_euclid(height) * width + col.rem_euclid(width)) as usize].is_alive()
+            } else {
+                row >= 0 && row < height && col >= 0 && col < width && cells[(row * width + col) as usize].is_alive()
+            }
+        };
+        let mut next = Vec::new();
+        for row in 0..height {
+            for col in 0..width {
+                let mut count = 0;
+                for (dy, dx) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dy, dx))) {
+                    count += ((dy, dx) != (0, 0) && alive(row + dy, col + dx)) as u32;
+                }
+                next.push(rule.next_by_count(cells[(row * width + col) as usize], count));
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn test_matches_cell_by_cell() {
+        for rule in &["B3/S23", "B36/S23", "B0/S8", "B2/S"] {
+            let rule: Rule = rule.parse().unwrap();
+            let (birth, survival) = rule.totalistic().unwrap();
+            for &(width, height) in &[(1, 3), (5, 7), (64, 4), (70, 9), (130, 6)] {
+                for &wrap in &[true, false] {
+                    let mut cells: Vec<Cell> = (0..width * height)
+                        .map(|idx| {
+                            if (idx * 7 + idx / width * 3) % 5 < 2 {
+                                Cell::ALIVE
+                            } else {
+                                Cell::DEAD
+                            }
+                        })
+                        .collect();
+                    let mut grid = BitGrid::from_cells(width, height, &cells);
+                    for generation in 0..4 {
+                        cells = tick_cells(&rule, width, height, wrap, &cells);
+                        let old = grid.clone();
+                        let changes = grid.tick(birth, survival, wrap);
+                        assert_eq!(changes, grid.changes(&old));
+                        assert_eq!(grid.population(), cells.iter().filter(|cell| cell.is_alive()).count() as u64);
+                        let mut unpacked = vec![Cell::DEAD; cells.len()];
+                        grid.write_cells(&mut unpacked);
+                        assert_eq!(
+                            unpacked, cells,
+                            "{} on {}x{}, wrap {}, generation {}",
+                            rule, width, height, wrap, generation
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
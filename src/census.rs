@@ -0,0 +1,468 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use wasm_bindgen::prelude::*;
+
+use crate::apgcode;
+use crate::library::Category;
+use crate::object::{self, Evolution, MAX_PERIOD};
+use crate::plane::SparsePlane;
+use crate::rule::Rule;
+use crate::Cell;
+
+/// How many generations objects that do not repeat are checked to not
+/// affect each other before they are counted apart.
+const UNKNOWN_HORIZON: u32 = 16;
+
+/// The largest still life that is tried as a pseudo still life.
+const PSEUDO_CELLS: usize = 20;
+
+/// The most parts of a still life looked at when splitting it.
+const PSEUDO_SUBSETS: usize = 50_000;
+
+/// The objects of one kind found by a census.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CensusEntry {
+    apgcode: String,
+    category: Category,
+    period: u32,
+    dy: i64,
+    dx: i64,
+    velocity: Option<String>,
+    count: u32,
+}
+
+#[wasm_bindgen]
+impl CensusEntry {
+    /// The object's apgcode, such as `xq4_153`, or `unknown` for objects
+    /// that do not repeat.
+    pub fn apgcode(&self) -> String {
+        self.apgcode.clone()
+    }
+
+    /// `still life`, `oscillator`, `spaceship` or `unknown`.
+    pub fn category(&self) -> String {
+        self.category.to_string()
+    }
+
+    /// The number of generations until the object looks the same again, 0
+    /// for unknown objects.
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    /// How many rows a spaceship moves down every period.
+    pub fn dy(&self) -> i32 {
+        self.dy as i32
+    }
+
+    /// How many columns a spaceship moves right every period.
+    pub fn dx(&self) -> i32 {
+        self.dx as i32
+    }
+
+    /// A spaceship's speed and direction, such as `c/4 diagonal`.
+    pub fn velocity(&self) -> Option<String> {
+        self.velocity.clone()
+    }
+
+    /// How many of the objects there are.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+impl CensusEntry {
+    pub fn kind(&self) -> Category {
+        self.category
+    }
+}
+
+/// The objects in a universe, counted by kind, most common first.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Census {
+    entries: Vec<CensusEntry>,
+}
+
+#[wasm_bindgen]
+impl Census {
+    /// The number of kinds of objects.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entry(&self, idx: usize) -> Option<CensusEntry> {
+        self.entries.get(idx).cloned()
+    }
+
+    /// The number of objects with an apgcode.
+    pub fn count(&self, apgcode: &str) -> u32 {
+        self.entries
+            .iter()
+            .find(|entry| entry.apgcode == apgcode)
+            .map_or(0, |entry| entry.count)
+    }
+
+    /// The number of objects of every kind.
+    pub fn objects(&self) -> u32 {
+        self.entries.iter().map(|entry| entry.count).sum()
+    }
+}
+
+impl Census {
+    /// Take a census of the cells that are not dead, given by row, column
+    /// and state, under `rule`. `wrap` gives the cell a position refers to,
+    /// or `None` when it is outside the universe, so objects can be followed
+    /// across the edges of a torus.
+    ///
+    /// Cells close enough to affect each other are grouped, then split into
+    /// connected objects again when those evolve the same on their own,
+    /// and still lifes are split into the still lifes they are made of.
+    /// Every object is then run on its own to classify it.
+    pub fn take<F>(rule: &Rule, cells: &[(i64, i64, Cell)], wrap: F) -> Census
+    where
+        F: Fn(i64, i64) -> Option<(i64, i64)>,
+    {
+        let states: HashMap<(i64, i64), Cell> = cells.iter().map(|&(row, col, cell)| ((row, col), cell)).collect();
+        let reach = 2 * rule.larger_than_life().map_or(1, |ltl| ltl.range as i64);
+        let occupied: HashSet<(i64, i64)> = states.keys().cloned().collect();
+        let mut entries: BTreeMap<String, CensusEntry> = BTreeMap::new();
+        let mut seen: HashSet<(i64, i64)> = HashSet::new();
+        let mut start: Vec<(i64, i64)> = occupied.iter().cloned().collect();
+        start.sort_unstable();
+
+        for start in start {
+            if seen.contains(&start) {
+                continue;
+            }
+            // Follow the cluster, giving its cells positions next to each
+            // other even where it crosses an edge.
+            let object::Cluster { positions, wraps_around } = object::cluster(&occupied, start, reach, &wrap);
+            seen.extend(positions.keys().cloned());
+
+            if wraps_around {
+                add(&mut entries, None);
+                continue;
+            }
+            let cluster: Vec<(i64, i64, Cell)> = positions
+                .iter()
+                .map(|(cell, &(row, col))| (row, col, states[cell]))
+                .collect();
+            for evolution in objects(rule, &cluster) {
+                add(&mut entries, evolution.as_ref());
+            }
+        }
+
+        let mut entries: Vec<CensusEntry> = entries.into_values().collect();
+        entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.apgcode.cmp(&b.apgcode)));
+        Census { entries }
+    }
+
+    pub fn entries(&self) -> &[CensusEntry] {
+        &self.entries
+    }
+}
+
+/// Count an object, or an unknown object for `None`.
+fn add(entries: &mut BTreeMap<String, CensusEntry>, evolution: Option<&Evolution>) {
+    let entry = match evolution {
+        Some(evolution) => CensusEntry {
+            apgcode: apgcode::encode(evolution),
+            category: evolution.category(),
+            period: evolution.period,
+            dy: evolution.dy,
+            dx: evolution.dx,
+            velocity: evolution.velocity(),
+            count: 0,
+        },
+        None => CensusEntry {
+            apgcode: String::from("unknown"),
+            category: Category::Unknown,
+            period: 0,
+            dy: 0,
+            dx: 0,
+            velocity: None,
+            count: 0,
+        },
+    };
+    entries.entry(entry.apgcode.clone()).or_insert(entry).count += 1;
+}
+
+/// The evolution of every object in a cluster of cells.
+fn objects(rule: &Rule, cluster: &[(i64, i64, Cell)]) -> Vec<Option<Evolution>> {
+    let evolution = object::evolve(rule, cluster, MAX_PERIOD);
+    let positions: HashSet<(i64, i64)> = cluster.iter().map(|&(row, col, _)| (row, col)).collect();
+    let mut components: Vec<Vec<(i64, i64, Cell)>> = Vec::new();
+    let mut seen = HashSet::new();
+    for &(row, col, _) in cluster {
+        if seen.contains(&(row, col)) {
+            continue;
+        }
+        let component = object::connected(&positions, (row, col));
+        seen.extend(component.iter().cloned());
+        components.push(cluster.iter().filter(|&&(row, col, _)| component.contains(&(row, col))).cloned().collect());
+    }
+
+    let horizon = evolution.as_ref().map_or(UNKNOWN_HORIZON, |evolution| evolution.period);
+    let pieces = if components.len() > 1 && independent(rule, cluster, &components, horizon) {
+        components
+            .into_iter()
+            .map(|component| {
+                let evolution = object::evolve(rule, &component, MAX_PERIOD);
+                (component, evolution)
+            })
+            .collect()
+    } else {
+        vec![(cluster.to_vec(), evolution)]
+    };
+
+    let mut objects = Vec::new();
+    for (cells, evolution) in pieces {
+        match evolution {
+            Some(evolution) if evolution.category() == Category::StillLife => {
+                for part in still_lifes(rule, &cells) {
+                    objects.push(Some(Evolution {
+                        period: 1,
+                        dy: 0,
+                        dx: 0,
+                        phases: vec![object::normalize(&part).0],
+                    }));
+                }
+            }
+            evolution => objects.push(evolution),
+        }
+    }
+    objects
+}
+
+/// Whether `components` evolve the same on their own as together in
+/// `cluster` for `generations` generations.
+fn independent(rule: &Rule, cluster: &[(i64, i64, Cell)], components: &[Vec<(i64, i64, Cell)>], generations: u32) -> bool {
+    let plane = |cells: &[(i64, i64, Cell)]| {
+        let mut plane = SparsePlane::new();
+        for &(row, col, cell) in cells {
+            plane.set(row, col, cell);
+        }
+        plane
+    };
+    let mut together = plane(cluster);
+    let mut apart: Vec<SparsePlane> = components.iter().map(|component| plane(component)).collect();
+    for _ in 0..generations {
+        together = together.tick(rule);
+        apart = apart.iter().map(|plane| plane.tick(rule)).collect();
+        let mut joined: Vec<(i64, i64, Cell)> = apart.iter().flat_map(|plane| plane.cells()).collect();
+        let mut cells: Vec<(i64, i64, Cell)> = together.cells().collect();
+        joined.sort_unstable();
+        cells.sort_unstable();
+        if joined != cells {
+            return false;
+        }
+    }
+    true
+}
+
+/// Whether live `cells` stay as they are on their own.
+fn stable(rule: &Rule, cells: &HashSet<(i64, i64)>) -> bool {
+    let mut checked = HashSet::new();
+    for &(row, col) in cells {
+        for (dy, dx) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dy, dx))) {
+            let (row, col) = (row + dy, col + dx);
+            if !checked.insert((row, col)) {
+                continue;
+            }
+            let mut index = 0;
+            for (bit, (dy, dx)) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dy, dx))).enumerate() {
+                index |= (cells.contains(&(row + dy, col + dx)) as usize) << bit;
+            }
+            let cell = if cells.contains(&(row, col)) { Cell::ALIVE } else { Cell::DEAD };
+            if rule.next(cell, index) != cell {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// The connected subsets of `cells` holding `root`, smallest first, or
+/// `None` when there are too many to look at.
+fn connected_subsets(cells: &HashSet<(i64, i64)>, root: (i64, i64)) -> Option<Vec<Vec<(i64, i64)>>> {
+    fn extend(
+        cells: &HashSet<(i64, i64)>,
+        subset: &mut Vec<(i64, i64)>,
+        candidates: Vec<(i64, i64)>,
+        excluded: &mut HashSet<(i64, i64)>,
+        subsets: &mut Vec<Vec<(i64, i64)>>,
+    ) -> bool {
+        subsets.push(subset.clone());
+        if subsets.len() > PSEUDO_SUBSETS {
+            return false;
+        }
+        let mut added = Vec::new();
+        for (idx, &cell) in candidates.iter().enumerate() {
+            let mut next: Vec<(i64, i64)> = candidates[idx + 1..].to_vec();
+            subset.push(cell);
+            excluded.insert(cell);
+            let mut new = Vec::new();
+            for (dy, dx) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dy, dx))) {
+                let neighbor = (cell.0 + dy, cell.1 + dx);
+                if cells.contains(&neighbor) && !excluded.contains(&neighbor) && !next.contains(&neighbor) {
+                    next.push(neighbor);
+                    new.push(neighbor);
+                }
+            }
+            // Cells first reached from this one are not candidates of the
+            // subsets left to try, which are reached through later ones.
+            let complete = extend(cells, subset, next, excluded, subsets);
+            subset.pop();
+            added.push(cell);
+            if !complete {
+                return false;
+            }
+        }
+        for cell in added {
+            excluded.remove(&cell);
+        }
+        true
+    }
+
+    let mut excluded: HashSet<(i64, i64)> = [root].iter().cloned().collect();
+    let candidates: Vec<(i64, i64)> = (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (root.0 + dy, root.1 + dx)))
+        .filter(|neighbor| cells.contains(neighbor) && *neighbor != root)
+        .collect();
+    let mut subsets = Vec::new();
+    if !extend(cells, &mut vec![root], candidates, &mut excluded, &mut subsets) {
+        return None;
+    }
+    subsets.sort_by_key(Vec::len);
+    Some(subsets)
+}
+
+/// Split `cells` into still lifes, each as small as possible, or `None`.
+fn partition(rule: &Rule, cells: &HashSet<(i64, i64)>) -> Option<Vec<HashSet<(i64, i64)>>> {
+    let root = match cells.iter().min() {
+        Some(&root) => root,
+        None => return Some(Vec::new()),
+    };
+    for subset in connected_subsets(cells, root)? {
+        let part: HashSet<(i64, i64)> = subset.into_iter().collect();
+        if !stable(rule, &part) {
+            continue;
+        }
+        let rest: HashSet<(i64, i64)> = cells.difference(&part).cloned().collect();
+        if let Some(mut parts) = partition(rule, &rest) {
+            parts.insert(0, part);
+            return Some(parts);
+        }
+    }
+    None
+}
+
+/// The still lifes a still life is made of: more than one for a pseudo
+/// still life, whose parts are still lifes on their own.
+fn still_lifes(rule: &Rule, cells: &[(i64, i64, Cell)]) -> Vec<Vec<(i64, i64, Cell)>> {
+    let whole = || vec![cells.to_vec()];
+    if rule.larger_than_life().is_some() || cells.len() > PSEUDO_CELLS {
+        return whole();
+    }
+    let live: HashSet<(i64, i64)> = cells.iter().map(|&(row, col, _)| (row, col)).collect();
+    match partition(rule, &live) {
+        Some(parts) if parts.len() > 1 => parts
+            .into_iter()
+            .map(|part| part.into_iter().map(|(row, col)| (row, col, Cell::ALIVE)).collect())
+            .collect(),
+        _ => whole(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rle::Rle;
+
+    fn census(text: &str) -> Census {
+        let rle = Rle::parse(text).unwrap();
+        let cells: Vec<(i64, i64, Cell)> =
+            rle.cells.iter().map(|&(row, col, cell)| (row as i64, col as i64, cell)).collect();
+        Census::take(&Rule::conway(), &cells, |row, col| Some((row, col)))
+    }
+
+    fn counts(census: &Census) -> Vec<(String, u32)> {
+        census.entries().iter().map(|entry| (entry.apgcode(), entry.count())).collect()
+    }
+
+    #[test]
+    fn test_census() {
+        // Two blocks, a blinker, a glider and a beehive.
+        let census = census("2o5b2o$2o5b2o3$3o5bo$8b2o$7bobo3$2b2o$bo2bo$2b2o!");
+        assert_eq!(
+            counts(&census),
+            vec![
+                (String::from("xs4_33"), 2),
+                (String::from("xp2_7"), 1),
+                (String::from("xq4_153"), 1),
+                (String::from("xs6_696"), 1),
+            ]
+        );
+        assert_eq!(census.objects(), 5);
+        let glider = census.entries().iter().find(|entry| entry.kind() == Category::Spaceship).unwrap();
+        assert_eq!((glider.period(), glider.velocity().as_deref()), (4, Some("c/4 diagonal")));
+        assert_eq!(census.count("xp2_7"), 1);
+        assert_eq!(census.count("xs6_696"), 1);
+    }
+
+    #[test]
+    fn test_pseudo_objects() {
+        // A bi-block: two blocks a cell apart, close enough to interact but
+        // still lifes on their own.
+        assert_eq!(counts(&census("2ob2o$2ob2o!")), vec![(String::from("xs4_33"), 2)]);
+        // Two tubs touching at a corner.
+        assert_eq!(counts(&census("bo$obo$bobo$2bobo$3bo!")), vec![(String::from("xs4_252"), 2)]);
+        // A snake is one still life, not a pseudo still life.
+        assert_eq!(counts(&census("2obo$ob2o!")), vec![(String::from("xs6_bd"), 1)]);
+        // A pre-block is three cells close together, which become a block.
+        assert_eq!(counts(&census("2o$o!")), vec![(String::from("unknown"), 1)]);
+        // A block next to a blinker that turns it into something else is
+        // one unknown object.
+        assert_eq!(counts(&census("2o$2o$3o!")), vec![(String::from("unknown"), 1)]);
+    }
+
+    #[test]
+    fn test_wrap() {
+        // A block and a blinker split across the edges of a 10 by 10 torus.
+        let cells: Vec<(i64, i64, Cell)> = [(0, 0), (0, 9), (9, 0), (9, 9), (4, 8), (4, 9), (4, 0)]
+            .iter()
+            .map(|&(row, col)| (row, col, Cell::ALIVE))
+            .collect();
+        let torus = |row: i64, col: i64| Some((row.rem_euclid(10), col.rem_euclid(10)));
+        let census = Census::take(&Rule::conway(), &cells, torus);
+        assert_eq!(counts(&census), vec![(String::from("xp2_7"), 1), (String::from("xs4_33"), 1)]);
+
+        // A row all the way around cannot be run on its own.
+        let row: Vec<(i64, i64, Cell)> = (0..10).map(|col| (3, col, Cell::ALIVE)).collect();
+        let census = Census::take(&Rule::conway(), &row, torus);
+        assert_eq!(counts(&census), vec![(String::from("unknown"), 1)]);
+    }
+
+    #[test]
+    fn test_long_objects() {
+        // A diagonal barge of 120 cells on a 128 by 128 torus has runs of
+        // more than 35 empty columns in its apgcode.
+        let cells: Vec<(i64, i64, Cell)> = (0..60)
+            .flat_map(|k| vec![(k + 4, k + 5, Cell::ALIVE), (k + 5, k + 4, Cell::ALIVE)])
+            .collect();
+        let torus = |row: i64, col: i64| Some((row.rem_euclid(128), col.rem_euclid(128)));
+        let census = Census::take(&Rule::conway(), &cells, torus);
+        assert_eq!(census.objects(), 1);
+        let barge = census.entry(0).unwrap();
+        assert_eq!(barge.kind(), Category::StillLife);
+        assert!(barge.apgcode().starts_with("xs120_"), "{}", barge.apgcode());
+        let cells = apgcode::decode(&barge.apgcode()).unwrap();
+        assert_eq!(cells.len(), 120);
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::Cell;
+
+/// When a universe settled into a cycle.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stabilization {
+    generation: u32,
+    period: u32,
+}
+
+#[wasm_bindgen]
+impl Stabilization {
+    /// The first generation of the cycle, counted from where the search
+    /// started.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// The number of generations in the cycle, 1 for a universe that no
+    /// longer changes.
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+}
+
+impl Stabilization {
+    pub fn new(generation: u32, period: u32) -> Stabilization {
+        Stabilization { generation, period }
+    }
+}
+
+/// A generation whose hash was seen before, whose cells are compared with
+/// the following generations to tell a cycle from a hash collision.
+struct Candidate {
+    cells: Vec<(i64, i64, Cell)>,
+    generation: u32,
+    period: u32,
+}
+
+/// Finds the cycle a universe settles into from the hashes of its states,
+/// one generation after another.
+#[derive(Default)]
+pub struct CycleDetector {
+    hashes: Vec<u64>,
+    first: HashMap<u64, u32>,
+    candidate: Option<Candidate>,
+}
+
+impl CycleDetector {
+    pub fn new() -> CycleDetector {
+        CycleDetector::default()
+    }
+
+    /// Look at the next generation, given the hash of its state and its
+    /// cells, sorted, and return the period of the cycle once it is found.
+    /// The cells are only asked for while a repeated hash is checked, so a
+    /// collision is never taken for a cycle: a cycle is found once the state
+    /// of the first repeated hash comes back, a period after it.
+    pub fn observe<F>(&mut self, hash: u64, cells: F) -> Option<u32>
+    where
+        F: Fn() -> Vec<(i64, i64, Cell)>,
+    {
+        let generation = self.hashes.len() as u32;
+        self.hashes.push(hash);
+
+        if let Some(candidate) = &self.candidate {
+            let period = generation - candidate.generation;
+            if cells() == candidate.cells {
+                return Some(period);
+            }
+            if period >= candidate.period {
+                self.candidate = None;
+            }
+        }
+
+        match self.first.get(&hash) {
+            Some(&earlier) if self.candidate.is_none() => {
+                self.candidate = Some(Candidate {
+                    cells: cells(),
+                    generation,
+                    period: generation - earlier,
+                });
+            }
+            Some(_) => {}
+            None => {
+                self.first.insert(hash, generation);
+            }
+        }
+        None
+    }
+
+    /// The generations that may start the cycle of `period` found by
+    /// `observe`, first to last: those whose hash comes back a period
+    /// later. The cycle starts at the first of them whose cells do, which
+    /// the last is known to.
+    pub fn starts(&self, period: u32) -> Vec<u32> {
+        let period = period as usize;
+        (0..self.hashes.len().saturating_sub(period))
+            .filter(|&generation| self.hashes[generation] == self.hashes[generation + period])
+            .map(|generation| generation as u32)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Observe states, given as numbers, until a cycle is found, then find
+    /// where it starts by comparing the states a period apart.
+    fn detect(states: &[u64], hash: impl Fn(u64) -> u64) -> Option<Stabilization> {
+        let mut detector = CycleDetector::new();
+        let period = states
+            .iter()
+            .find_map(|&state| detector.observe(hash(state), || vec![(0, state as i64, Cell::ALIVE)]))?;
+        let generation = detector
+            .starts(period)
+            .into_iter()
+            .find(|&generation| states[generation as usize] == states[(generation + period) as usize])?;
+        Some(Stabilization::new(generation, period))
+    }
+
+    fn stabilization(generation: u32, period: u32) -> Option<Stabilization> {
+        Some(Stabilization::new(generation, period))
+    }
+
+    #[test]
+    fn test_cycle() {
+        let states = [5, 4, 1, 2, 3, 1, 2, 3, 1, 2, 3];
+        assert_eq!(detect(&states, |state| state), stabilization(2, 3));
+        assert_eq!(detect(&[7, 7, 7], |state| state), stabilization(0, 1));
+        assert_eq!(detect(&[1, 2, 3, 4], |state| state), None);
+    }
+
+    #[test]
+    fn test_collision() {
+        // States 1 and 2 hash the same, and are told apart by their cells.
+        let hash = |state| if state == 2 { 1 } else { state };
+        let states = [1, 2, 3, 4, 5, 6, 7, 5, 6, 7, 5];
+        assert_eq!(detect(&states, hash), stabilization(4, 3));
+        assert_eq!(detect(&[1, 2, 3, 2, 3], hash), None);
+        assert_eq!(detect(&[1, 2, 3, 2, 3, 2], hash), stabilization(1, 2));
+
+        // States 9 and 5 hash the same a period apart, before the cycle
+        // starts.
+        let hash = |state| if state == 9 { 5 } else { state };
+        assert_eq!(detect(&[1, 9, 4, 5, 6, 4, 5, 6, 4], hash), stabilization(2, 3));
+    }
+}
@@ -0,0 +1,729 @@
+use std::collections::HashMap;
+
+use crate::macrocell::{self, Macrocell, MAX_LEVEL};
+use crate::rule::Rule;
+use crate::Cell;
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// A square of `2^level` cells per side, made of four squares of half the
+/// size, or a single cell at level 0.
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    children: [NodeId; 4],
+    level: u8,
+    population: u64,
+}
+
+/// A rough size of one node including its entries in the lookup and result
+/// maps, used to turn the memory limit into a number of nodes.
+const NODE_BYTES: usize = 96;
+
+/// Whether a rule can be run by `HashLife`: it must only have two states and
+/// only look at the eight nearest neighbors.
+pub fn supports(rule: &Rule) -> bool {
+    rule.states() == 2 && rule.larger_than_life().is_none()
+}
+
+/// The HashLife algorithm: the plane is a quadtree of canonical, shared
+/// nodes, and the future of the center of every node is memoized so that
+/// repeated structure in space and time is only ever computed once. This
+/// makes it possible to jump huge numbers of generations ahead for
+/// patterns such as guns and breeders.
+#[derive(Clone, Debug)]
+pub struct HashLife {
+    nodes: Vec<Node>,
+    lookup: HashMap<[NodeId; 4], NodeId>,
+    /// The node a node becomes after `2^step` generations, keyed by node and
+    /// step.
+    results: HashMap<(NodeId, u8), NodeId>,
+    /// The empty node of each level.
+    empty: Vec<NodeId>,
+    table: Vec<bool>,
+    root: NodeId,
+    /// The plane coordinates of the top left cell of the root. The root is
+    /// always centered on the origin of the plane.
+    origin: (i64, i64),
+    max_nodes: usize,
+}
+
+impl HashLife {
+    /// Create an empty plane running `rule`, which must be `supports`ed.
+    pub fn new(rule: &Rule) -> HashLife {
+        let leaf = |alive| Node {
+            children: [DEAD; 4],
+            level: 0,
+            population: alive,
+        };
+        let mut hashlife = HashLife {
+            nodes: vec![leaf(0), leaf(1)],
+            lookup: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            table: Vec::new(),
+            root: DEAD,
+            origin: (0, 0),
+            max_nodes: 64 * 1024 * 1024 / NODE_BYTES,
+        };
+        hashlife.set_rule(rule);
+        hashlife.root = hashlife.empty(3);
+        hashlife.origin = (-4, -4);
+        hashlife
+    }
+
+    /// Change the rule, forgetting every memoized result if it differs.
+    pub fn set_rule(&mut self, rule: &Rule) {
+        let table: Vec<bool> = (0..512)
+            .map(|index| {
+                let cell = if index & (1 << 4) != 0 {
+                    Cell::ALIVE
+                } else {
+                    Cell::DEAD
+                };
+                rule.next(cell, index).is_alive()
+            })
+            .collect();
+        if table != self.table {
+            self.table = table;
+            self.results.clear();
+        }
+    }
+
+    /// Limit the memory used by the node cache. When a step exceeds it the
+    /// cache is garbage collected, keeping only the current pattern, and the
+    /// step is taken again in smaller steps.
+    pub fn set_memory_limit(&mut self, megabytes: u32) {
+        self.max_nodes = (megabytes as usize * 1024 * 1024 / NODE_BYTES).max(1024);
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    fn node(&self, id: NodeId) -> Node {
+        self.nodes[id as usize]
+    }
+
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&id) = self.lookup.get(&children) {
+            return id;
+        }
+        let level = self.node(children[0]).level + 1;
+        let population = children.iter().map(|&child| self.node(child).population).sum();
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            children,
+            level,
+            population,
+        });
+        self.lookup.insert(children, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let child = *self.empty.last().unwrap();
+            let empty = self.join([child; 4]);
+            self.empty.push(empty);
+        }
+        self.empty[level as usize]
+    }
+
+    /// The side of the root square.
+    fn size(&self) -> i64 {
+        1 << self.node(self.root).level
+    }
+
+    /// Surround the root with empty space, doubling its size and keeping it
+    /// centered.
+    fn expand(&mut self) {
+        let root = self.node(self.root);
+        let [nw, ne, sw, se] = root.children;
+        let empty = self.empty(root.level - 1);
+        let nw = self.join([empty, empty, empty, nw]);
+        let ne = self.join([empty, empty, ne, empty]);
+        let sw = self.join([empty, sw, empty, empty]);
+        let se = self.join([se, empty, empty, empty]);
+        let half = self.size() / 2;
+        self.root = self.join([nw, ne, sw, se]);
+        self.origin = (self.origin.0 - half, self.origin.1 - half);
+    }
+
+    fn contains(&self, row: i64, col: i64) -> bool {
+        let size = self.size();
+        let (top, left) = self.origin;
+        row >= top && row < top + size && col >= left && col < left + size
+    }
+
+    pub fn get(&self, row: i64, col: i64) -> Cell {
+        if !self.contains(row, col) {
+            return Cell::DEAD;
+        }
+        let (mut row, mut col) = (row - self.origin.0, col - self.origin.1);
+        let mut node = self.node(self.root);
+        while node.level > 0 {
+            let half = 1 << (node.level - 1);
+            let quadrant = (row >= half) as usize * 2 + (col >= half) as usize;
+            row %= half;
+            col %= half;
+            node = self.node(node.children[quadrant]);
+        }
+        Cell::new(node.population as u8)
+    }
+
+    fn set_in(&mut self, id: NodeId, row: i64, col: i64, alive: bool) -> NodeId {
+        let node = self.node(id);
+        if node.level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+        let half = 1 << (node.level - 1);
+        let quadrant = (row >= half) as usize * 2 + (col >= half) as usize;
+        let mut children = node.children;
+        children[quadrant] = self.set_in(children[quadrant], row % half, col % half, alive);
+        self.join(children)
+    }
+
+    /// Set a cell. Dying states are not supported, so any state other than
+    /// dead makes the cell alive. Cells beyond the deepest quadtree, `2^61`
+    /// cells from the origin, are ignored.
+    pub fn set(&mut self, row: i64, col: i64, cell: Cell) {
+        while !self.contains(row, col) {
+            if self.node(self.root).level == MAX_LEVEL {
+                return;
+            }
+            self.expand();
+        }
+        let (top, left) = self.origin;
+        self.root = self.set_in(self.root, row - top, col - left, cell != Cell::DEAD);
+    }
+
+    pub fn clear(&mut self) {
+        let level = self.node(self.root).level;
+        self.root = self.empty(level);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.population() == 0
+    }
+
+    fn collect_cells(&self, id: NodeId, top: i64, left: i64, cells: &mut Vec<(i64, i64, Cell)>) {
+        let node = self.node(id);
+        if node.population == 0 {
+            return;
+        }
+        if node.level == 0 {
+            cells.push((top, left, Cell::ALIVE));
+            return;
+        }
+        let half = 1 << (node.level - 1);
+        for (quadrant, &child) in node.children.iter().enumerate() {
+            let (row, col) = ((quadrant / 2) as i64 * half, (quadrant % 2) as i64 * half);
+            self.collect_cells(child, top + row, left + col, cells);
+        }
+    }
+
+    /// The position and state of every live cell.
+    pub fn cells(&self) -> Vec<(i64, i64, Cell)> {
+        let mut cells = Vec::with_capacity(self.population() as usize);
+        self.collect_cells(self.root, self.origin.0, self.origin.1, &mut cells);
+        cells
+    }
+
+    /// The cells of the `height` by `width` region whose top left cell is at
+    /// `row` and `col`, row by row. Only the parts of the tree overlapping
+    /// the region are visited.
+    pub fn region(&self, row: i64, col: i64, height: u32, width: u32) -> Vec<Cell> {
+        let (height, width) = (height as i64, width as i64);
+        let mut region = vec![Cell::DEAD; (height * width) as usize];
+        let mut stack = vec![(self.root, self.origin.0, self.origin.1)];
+        while let Some((id, top, left)) = stack.pop() {
+            let node = self.node(id);
+            let size = 1 << node.level;
+            if node.population == 0
+                || top >= row + height
+                || left >= col + width
+                || top + size <= row
+                || left + size <= col
+            {
+                continue;
+            }
+            if node.level == 0 {
+                region[((top - row) * width + left - col) as usize] = Cell::ALIVE;
+                continue;
+            }
+            let half = size / 2;
+            for (quadrant, &child) in node.children.iter().enumerate() {
+                let (dy, dx) = ((quadrant / 2) as i64 * half, (quadrant % 2) as i64 * half);
+                stack.push((child, top + dy, left + dx));
+            }
+        }
+        region
+    }
+
+    /// The center square of a node, half its size.
+    fn center(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.node(id).children;
+        let children = [
+            self.node(nw).children[3],
+            self.node(ne).children[2],
+            self.node(sw).children[1],
+            self.node(se).children[0],
+        ];
+        self.join(children)
+    }
+
+    /// Advance a level 2 node one generation, returning its level 1 center.
+    fn step_leaf(&mut self, id: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        for (quadrant, &child) in self.node(id).children.iter().enumerate() {
+            for (leaf_quadrant, &leaf) in self.node(child).children.iter().enumerate() {
+                let row = quadrant / 2 * 2 + leaf_quadrant / 2;
+                let col = quadrant % 2 * 2 + leaf_quadrant % 2;
+                cells[row][col] = leaf == ALIVE;
+            }
+        }
+        let mut center = [DEAD; 4];
+        for (quadrant, next) in center.iter_mut().enumerate() {
+            let (row, col) = (quadrant / 2 + 1, quadrant % 2 + 1);
+            let mut index = 0;
+            for bit in 0..9 {
+                index |= (cells[row + bit / 3 - 1][col + bit % 3 - 1] as usize) << bit;
+            }
+            if self.table[index] {
+                *next = ALIVE;
+            }
+        }
+        self.join(center)
+    }
+
+    /// The center of a node of level `k` after `2^step` generations, where
+    /// `step <= k - 2`, or `None` once the node cache has grown past
+    /// `max_nodes` nodes.
+    fn advance(&mut self, id: NodeId, step: u8, max_nodes: usize) -> Option<NodeId> {
+        let node = self.node(id);
+        if node.population == 0 {
+            return Some(self.empty(node.level - 1));
+        }
+        if let Some(&result) = self.results.get(&(id, step)) {
+            return Some(result);
+        }
+        if self.nodes.len() > max_nodes {
+            return None;
+        }
+        let result = if node.level == 2 {
+            self.step_leaf(id)
+        } else {
+            let [nw, ne, sw, se] = node.children;
+            let [_, nw_ne, nw_sw, nw_se] = self.node(nw).children;
+            let [ne_nw, _, ne_sw, ne_se] = self.node(ne).children;
+            let [sw_nw, sw_ne, _, sw_se] = self.node(sw).children;
+            let [se_nw, se_ne, se_sw, _] = self.node(se).children;
+
+            // The nine overlapping squares of half the node's size.
+            let squares = [
+                nw,
+                self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+                ne,
+                self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+                self.join([nw_se, ne_sw, sw_ne, se_nw]),
+                self.join([ne_sw, ne_se, se_nw, se_ne]),
+                sw,
+                self.join([sw_ne, se_nw, sw_se, se_sw]),
+                se,
+            ];
+
+            // At full speed each half of the time step is taken by the
+            // recursive results; otherwise the first half is skipped.
+            let full_speed = step == node.level - 2;
+            let mut inner = [DEAD; 9];
+            for (square, &id) in inner.iter_mut().zip(squares.iter()) {
+                *square = if full_speed {
+                    self.advance(id, step - 1, max_nodes)?
+                } else {
+                    self.center(id)
+                };
+            }
+
+            let quadrants = [[0, 1, 3, 4], [1, 2, 4, 5], [3, 4, 6, 7], [4, 5, 7, 8]];
+            let mut result = [DEAD; 4];
+            for (next, quadrant) in result.iter_mut().zip(quadrants.iter()) {
+                let joined = self.join([
+                    inner[quadrant[0]],
+                    inner[quadrant[1]],
+                    inner[quadrant[2]],
+                    inner[quadrant[3]],
+                ]);
+                *next = self.advance(joined, if full_speed { step - 1 } else { step }, max_nodes)?;
+            }
+            self.join(result)
+        };
+        self.results.insert((id, step), result);
+        Some(result)
+    }
+
+    /// Whether every live cell lies within the center square of the root.
+    fn is_centered(&self) -> bool {
+        let root = self.node(self.root);
+        root.children.iter().enumerate().all(|(quadrant, &child)| {
+            let child = self.node(child);
+            let inner = self.node(child.children[3 - quadrant]);
+            child.population == inner.population
+        })
+    }
+
+    /// Advance the plane by `2^step` generations, with at most `max_nodes`
+    /// nodes in the cache. When they run out the cache is garbage collected
+    /// and the step taken again as two steps of half as many generations,
+    /// down to single generations, which are taken whatever they need.
+    ///
+    /// The root is never expanded past `MAX_LEVEL`, so `step` must be at most
+    /// `MAX_LEVEL - 3`, and cells carried beyond the deepest quadtree are lost.
+    fn step_pow2(&mut self, step: u8, max_nodes: usize) {
+        let (root, origin) = (self.root, self.origin);
+        while self.node(self.root).level < MAX_LEVEL
+            && (self.node(self.root).level < step + 2 || !self.is_centered())
+        {
+            self.expand();
+        }
+        if self.node(self.root).level < MAX_LEVEL {
+            self.expand();
+        }
+        let quarter = self.size() / 4;
+        match self.advance(self.root, step, max_nodes) {
+            Some(root) => {
+                self.root = root;
+                self.origin = (self.origin.0 + quarter, self.origin.1 + quarter);
+            }
+            None if step == 0 => {
+                self.root = root;
+                self.origin = origin;
+                self.collect_garbage();
+                self.step_pow2(0, usize::MAX);
+            }
+            None => {
+                self.root = root;
+                self.origin = origin;
+                self.collect_garbage();
+                // A pattern that takes most of the limit on its own is given
+                // room to grow rather than be advanced a generation at a
+                // time.
+                let max_nodes = self.max_nodes.max(2 * self.nodes.len());
+                self.step_pow2(step - 1, max_nodes);
+                self.step_pow2(step - 1, max_nodes);
+            }
+        }
+    }
+
+    /// Advance the plane by `generations` generations, one power of two at a
+    /// time, garbage collecting the node cache between powers when it has
+    /// grown past the memory limit.
+    pub fn step(&mut self, generations: u64) {
+        // Powers of two too large for one step of the deepest quadtree are
+        // taken as several of the largest steps.
+        let max_step = MAX_LEVEL - 3;
+        for step in 0..64u8 {
+            if generations & (1 << step) != 0 {
+                let (step, times) = match step.checked_sub(max_step) {
+                    Some(extra) => (max_step, 1 << extra),
+                    None => (step, 1),
+                };
+                for _ in 0..times {
+                    self.step_pow2(step, self.max_nodes);
+                    if self.nodes.len() > self.max_nodes {
+                        self.collect_garbage();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Build the node for the `2^level` cells wide square of an 8x8 block of
+    /// cells whose top left cell is at `row` and `col`, given as one byte per
+    /// row with bit `col` set for live cells.
+    fn block(&mut self, rows: &[u8; 8], level: u8, row: usize, col: usize) -> NodeId {
+        if level == 0 {
+            return if rows[row] >> col & 1 != 0 { ALIVE } else { DEAD };
+        }
+        let half = 1 << (level - 1);
+        let nw = self.block(rows, level - 1, row, col);
+        let ne = self.block(rows, level - 1, row, col + half);
+        let sw = self.block(rows, level - 1, row + half, col);
+        let se = self.block(rows, level - 1, row + half, col + half);
+        self.join([nw, ne, sw, se])
+    }
+
+    fn import(
+        &mut self,
+        macrocell: &Macrocell,
+        number: usize,
+        level: u8,
+        imported: &mut Vec<Option<NodeId>>,
+    ) -> NodeId {
+        if number == 0 {
+            return self.empty(level);
+        }
+        if let Some(id) = imported[number] {
+            return id;
+        }
+        let id = match macrocell.nodes[number - 1] {
+            macrocell::Node::Leaf(rows) => self.block(&rows, 3, 0, 0),
+            macrocell::Node::States(states) => {
+                self.join(states.map(|state| if state != 0 { ALIVE } else { DEAD }))
+            }
+            macrocell::Node::Branch(level, children) => {
+                let mut ids = [DEAD; 4];
+                for (id, &child) in ids.iter_mut().zip(children.iter()) {
+                    *id = self.import(macrocell, child, level - 1, imported);
+                }
+                self.join(ids)
+            }
+        };
+        imported[number] = Some(id);
+        id
+    }
+
+    /// Replace the pattern with a macrocell pattern, centered on the origin.
+    /// Every node of the pattern is only visited once, so huge patterns load
+    /// in time proportional to the size of their quadtree rather than to
+    /// their number of cells. Any state other than dead makes a cell alive.
+    pub fn load_macrocell(&mut self, macrocell: &Macrocell) {
+        if macrocell.root == 0 {
+            self.clear();
+            return;
+        }
+        let mut imported = vec![None; macrocell.nodes.len() + 1];
+        self.root = self.import(macrocell, macrocell.root, macrocell.level(), &mut imported);
+        let half = self.size() / 2;
+        self.origin = (-half, -half);
+        while self.node(self.root).level < 3 {
+            self.expand();
+        }
+    }
+
+    fn export(&self, id: NodeId, macrocell: &mut Macrocell, exported: &mut HashMap<NodeId, usize>) -> usize {
+        let node = self.node(id);
+        if node.population == 0 {
+            return 0;
+        }
+        if let Some(&number) = exported.get(&id) {
+            return number;
+        }
+        let exported_node = if node.level == 3 {
+            let mut cells = Vec::new();
+            self.collect_cells(id, 0, 0, &mut cells);
+            let mut rows = [0u8; 8];
+            for (row, col, _) in cells {
+                rows[row as usize] |= 1 << col;
+            }
+            macrocell::Node::Leaf(rows)
+        } else {
+            let mut children = [0; 4];
+            for (number, &child) in children.iter_mut().zip(node.children.iter()) {
+                *number = self.export(child, macrocell, exported);
+            }
+            macrocell::Node::Branch(node.level, children)
+        };
+        let number = macrocell.add(exported_node);
+        exported.insert(id, number);
+        number
+    }
+
+    /// The pattern as a macrocell pattern, sharing the tree's nodes without
+    /// visiting its cells.
+    pub fn to_macrocell(&self) -> Macrocell {
+        let mut macrocell = Macrocell::default();
+        let mut exported = HashMap::new();
+        macrocell.root = self.export(self.root, &mut macrocell, &mut exported);
+        macrocell
+    }
+
+    /// Rebuild the node cache with only the nodes of the current pattern,
+    /// forgetting every memoized result.
+    pub fn collect_garbage(&mut self) {
+        let mut collected = HashLife {
+            nodes: self.nodes[..2].to_vec(),
+            lookup: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            table: self.table.clone(),
+            root: DEAD,
+            origin: self.origin,
+            max_nodes: self.max_nodes,
+        };
+        let mut copied = HashMap::new();
+        collected.root = collected.copy_from(self, self.root, &mut copied);
+        *self = collected;
+    }
+
+    fn copy_from(
+        &mut self,
+        other: &HashLife,
+        id: NodeId,
+        copied: &mut HashMap<NodeId, NodeId>,
+    ) -> NodeId {
+        if id == DEAD || id == ALIVE {
+            return id;
+        }
+        if let Some(&copy) = copied.get(&id) {
+            return copy;
+        }
+        let children = other.node(id).children;
+        let mut copies = [DEAD; 4];
+        for (copy, &child) in copies.iter_mut().zip(children.iter()) {
+            *copy = self.copy_from(other, child, copied);
+        }
+        let copy = self.join(copies);
+        copied.insert(id, copy);
+        copy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plane::SparsePlane;
+
+    fn glider_gun(hashlife: &mut HashLife, plane: &mut SparsePlane) {
+        let gun = [
+            "........................O...........",
+            "......................O.O...........",
+            "............OO......OO............OO",
+            "...........O...O....OO............OO",
+            "OO........O.....O...OO..............",
+            "OO........O...O.OO....O.O...........",
+            "..........O.....O.......O...........",
+            "...........O...O....................",
+            "............OO......................",
+        ];
+        for (row, line) in gun.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                if c == 'O' {
+                    hashlife.set(row as i64, col as i64, Cell::ALIVE);
+                    plane.set(row as i64, col as i64, Cell::ALIVE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_sparse_plane() {
+        let rule = Rule::conway();
+        let mut hashlife = HashLife::new(&rule);
+        let mut plane = SparsePlane::new();
+        glider_gun(&mut hashlife, &mut plane);
+
+        for &generations in &[1, 2, 5, 30, 123] {
+            hashlife.step(generations);
+            for _ in 0..generations {
+                plane = plane.tick(&rule);
+            }
+            let mut expected: Vec<(i64, i64)> = plane.cells().map(|(row, col, _)| (row, col)).collect();
+            let mut cells: Vec<(i64, i64)> =
+                hashlife.cells().into_iter().map(|(row, col, _)| (row, col)).collect();
+            expected.sort();
+            cells.sort();
+            assert_eq!(cells, expected, "after {} more generations", generations);
+        }
+    }
+
+    #[test]
+    fn test_macrocell() {
+        let rule = Rule::conway();
+        let mut hashlife = HashLife::new(&rule);
+        let mut plane = SparsePlane::new();
+        glider_gun(&mut hashlife, &mut plane);
+        let mut expected = hashlife.cells();
+        expected.sort();
+
+        let macrocell = hashlife.to_macrocell();
+        let mut cells = macrocell.cells();
+        cells.sort();
+        assert_eq!(cells, expected);
+
+        let mut loaded = HashLife::new(&rule);
+        loaded.load_macrocell(&Macrocell::parse(&macrocell.to_string()).unwrap());
+        let mut cells = loaded.cells();
+        cells.sort();
+        assert_eq!(cells, expected);
+
+        // A pattern 2^20 cells wide loads without visiting its cells.
+        let mut huge = String::from("[M2]\n**$**$\n");
+        for level in 4..=20 {
+            let child = level - 3;
+            huge.push_str(&format!("{} {} {} {} {}\n", level, child, child, child, child));
+        }
+        loaded.load_macrocell(&Macrocell::parse(&huge).unwrap());
+        assert_eq!(loaded.population(), 4 << (2 * 17));
+        assert_eq!(loaded.get(-(1 << 19), -(1 << 19)), Cell::ALIVE);
+        assert_eq!(loaded.get((1 << 19) - 8, (1 << 19) - 7), Cell::ALIVE);
+        assert_eq!(loaded.get((1 << 19) - 6, 0), Cell::DEAD);
+        assert!(loaded.node_count() < 1000);
+
+        // So does one as wide as the deepest quadtree, which can still be
+        // run, while deeper ones are rejected.
+        let mut deepest = String::from("[M2]\n**$**$\n");
+        for level in 4..MAX_LEVEL {
+            deepest.push_str(&format!("{} 0 0 0 {}\n", level, level - 3));
+        }
+        deepest.push_str(&format!("{} {} 0 0 0\n", MAX_LEVEL, MAX_LEVEL - 3));
+        loaded.load_macrocell(&Macrocell::parse(&deepest).unwrap());
+        assert_eq!(loaded.cells(), vec![(-8, -8, Cell::ALIVE), (-8, -7, Cell::ALIVE), (-7, -8, Cell::ALIVE), (-7, -7, Cell::ALIVE)]);
+        loaded.step(u64::MAX);
+        assert_eq!(loaded.population(), 4);
+        deepest.push_str(&format!("{} 0 0 0 {}\n", MAX_LEVEL + 1, MAX_LEVEL - 2));
+        assert!(Macrocell::parse(&deepest).is_err());
+    }
+
+    #[test]
+    fn test_long_run_and_garbage_collection() {
+        let rule = Rule::conway();
+        let mut hashlife = HashLife::new(&rule);
+        let mut plane = SparsePlane::new();
+        glider_gun(&mut hashlife, &mut plane);
+
+        // The gun emits one glider every 30 generations, each of which
+        // carries on forever, on top of its own 36 cells.
+        hashlife.step(30 * 1000);
+        let population = hashlife.population();
+        assert!((36 + 5 * 999..=36 + 5 * 1001 + 20).contains(&population));
+
+        let nodes = hashlife.node_count();
+        hashlife.collect_garbage();
+        assert!(hashlife.node_count() < nodes);
+        assert_eq!(hashlife.population(), population);
+        assert!(hashlife.results.is_empty());
+        hashlife.step(30);
+        assert!(hashlife.population() > population);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let rule = Rule::conway();
+        let mut hashlife = HashLife::new(&rule);
+        let mut plane = SparsePlane::new();
+        glider_gun(&mut hashlife, &mut plane);
+        let mut limited = hashlife.clone();
+        hashlife.step(1 << 10);
+        let unlimited = hashlife.node_count();
+
+        // A single step of 2^10 generations, which needs more nodes than
+        // that, stays within the limit by collecting garbage and taking
+        // smaller steps.
+        assert!(unlimited > 2048);
+        limited.max_nodes = 2048;
+        limited.step_pow2(10, 2048);
+        assert!(limited.node_count() <= 2048);
+        let mut cells = limited.cells();
+        let mut expected = hashlife.cells();
+        cells.sort();
+        expected.sort();
+        assert_eq!(cells, expected);
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+
+use crate::Cell;
+
+/// The number of steps that can be undone unless set otherwise.
+pub const UNDO_LIMIT: usize = 100;
+
+/// A cell that changed, by its position in the cell buffer, or in the plane
+/// for an unbounded universe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub row: i64,
+    pub col: i64,
+    pub old: Cell,
+    pub new: Cell,
+}
+
+/// The width, height and cell size of a universe.
+pub type Dimensions = (u32, u32, u8);
+
+/// A step that can be undone, stored as the cells it changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// Cells edited by one operation.
+    Edit(Vec<Change>),
+    /// The cells that changed over one `tick`, `step_by` or
+    /// `run_until_stable`.
+    Generation(Vec<Change>),
+    /// A resize, which clears the cell buffer, with the cells it cleared.
+    Resize {
+        old: Dimensions,
+        new: Dimensions,
+        cleared: Vec<Change>,
+    },
+}
+
+/// Bounded stacks of steps to undo and redo. Edits to cells are collected
+/// as they are made, and become one step when the operation making them is
+/// committed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+    pending: Vec<Change>,
+    limit: usize,
+    /// Whether generations are recorded as well as edits and resizes.
+    generations: bool,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History::new(UNDO_LIMIT)
+    }
+}
+
+impl History {
+    /// Keep the last `limit` steps, or none for 0.
+    pub fn new(limit: usize) -> History {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            pending: Vec::new(),
+            limit,
+            generations: false,
+        }
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.commit();
+        while self.undo.len() > limit {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Whether steps are recorded at all.
+    pub fn is_recording(&self) -> bool {
+        self.limit > 0
+    }
+
+    pub fn set_generations(&mut self, generations: bool) {
+        self.generations = generations;
+    }
+
+    /// Whether generations are recorded, which they are not by default.
+    pub fn is_recording_generations(&self) -> bool {
+        self.generations && self.is_recording()
+    }
+
+    /// Note an edit from `old` to `new` of the cell at `row` and `col`.
+    pub fn record(&mut self, row: i64, col: i64, old: Cell, new: Cell) {
+        if old != new && self.is_recording() {
+            self.pending.push(Change { row, col, old, new });
+        }
+    }
+
+    /// Make the edits noted since the last commit one step.
+    pub fn commit(&mut self) {
+        if !self.pending.is_empty() {
+            let changes = std::mem::take(&mut self.pending);
+            self.push(Entry::Edit(changes));
+        }
+    }
+
+    /// Forget the edits noted since the last commit, made by undoing or
+    /// redoing a step.
+    pub fn discard(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Add a step after the edits not yet committed, which can no longer
+    /// be redone past.
+    pub fn push(&mut self, entry: Entry) {
+        if !self.is_recording() {
+            return;
+        }
+        if !matches!(entry, Entry::Edit(_)) {
+            self.commit();
+        }
+        self.push_undo(entry);
+        self.redo.clear();
+    }
+
+    fn push_undo(&mut self, entry: Entry) {
+        self.undo.push_back(entry);
+        if self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Note a generation that was not recorded. Edits and resizes can still
+    /// be undone on the cells it left, but the generations recorded before
+    /// it and the steps undone can no longer be taken back.
+    pub fn skip_generation(&mut self) {
+        self.commit();
+        self.undo.retain(|entry| !matches!(entry, Entry::Generation(_)));
+        self.redo.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || !self.pending.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The last step, to be undone and then handed to `undone`.
+    pub fn take_undo(&mut self) -> Option<Entry> {
+        self.commit();
+        self.undo.pop_back()
+    }
+
+    pub fn undone(&mut self, entry: Entry) {
+        self.redo.push(entry);
+    }
+
+    /// The last step undone, to be redone and then handed to `redone`.
+    pub fn take_redo(&mut self) -> Option<Entry> {
+        self.redo.pop()
+    }
+
+    pub fn redone(&mut self, entry: Entry) {
+        self.push_undo(entry);
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(col: i64) -> Change {
+        Change {
+            row: 0,
+            col,
+            old: Cell::DEAD,
+            new: Cell::ALIVE,
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = History::new(2);
+        assert!(!history.can_undo());
+        history.record(0, 0, Cell::ALIVE, Cell::ALIVE);
+        assert!(!history.can_undo());
+        history.record(0, 1, Cell::DEAD, Cell::ALIVE);
+        history.record(0, 2, Cell::DEAD, Cell::ALIVE);
+        assert!(history.can_undo());
+        history.push(Entry::Generation(vec![change(3)]));
+        history.record(0, 4, Cell::DEAD, Cell::ALIVE);
+        history.commit();
+
+        // The oldest step fell off.
+        assert_eq!(history.take_undo(), Some(Entry::Edit(vec![change(4)])));
+        history.undone(Entry::Edit(vec![change(4)]));
+        assert!(history.can_redo());
+        assert_eq!(history.take_undo(), Some(Entry::Generation(vec![change(3)])));
+        assert_eq!(history.take_undo(), None);
+        assert_eq!(history.take_redo(), Some(Entry::Edit(vec![change(4)])));
+
+        // A new step can no longer be redone past.
+        history.undone(Entry::Edit(vec![change(4)]));
+        history.record(0, 5, Cell::DEAD, Cell::ALIVE);
+        history.commit();
+        assert!(!history.can_redo());
+
+        // Redoing keeps to the limit too.
+        assert_eq!(history.take_undo(), Some(Entry::Edit(vec![change(5)])));
+        history.undone(Entry::Edit(vec![change(5)]));
+        history.redone(Entry::Edit(vec![change(7)]));
+        history.redone(Entry::Edit(vec![change(8)]));
+        history.redone(Entry::Edit(vec![change(9)]));
+        assert_eq!(history.undo.len(), 2);
+
+        // A generation that is not recorded keeps the edits, but not the
+        // generations.
+        history.push(Entry::Generation(vec![change(10)]));
+        history.record(0, 11, Cell::DEAD, Cell::ALIVE);
+        history.skip_generation();
+        assert_eq!(history.take_undo(), Some(Entry::Edit(vec![change(11)])));
+        assert_eq!(history.take_undo(), None);
+
+        assert!(!history.is_recording_generations());
+        history.set_generations(true);
+        assert!(history.is_recording_generations());
+
+        history.set_limit(0);
+        assert!(!history.can_undo());
+        assert!(!history.is_recording_generations());
+        history.record(0, 6, Cell::DEAD, Cell::ALIVE);
+        assert!(!history.can_undo());
+    }
+}
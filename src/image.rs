@@ -0,0 +1,145 @@
+use crate::rule::Rule;
+use crate::Cell;
+
+/// A red, green and blue color.
+pub type Rgb = [u8; 3];
+
+/// The colors the canvas is drawn with, from the shaders in `render`.
+const DEAD: Rgb = [77, 77, 77];
+const ALIVE: Rgb = [179, 204, 181];
+const GRID: Rgb = [38, 64, 38];
+
+/// Split a `0xRRGGBB` color into its parts.
+fn rgb(color: u32) -> Rgb {
+    [(color >> 16) as u8, (color >> 8) as u8, color as u8]
+}
+
+/// How bright a color looks, from 0 to 255.
+pub fn luminance([red, green, blue]: Rgb) -> u8 {
+    ((299 * red as u32 + 587 * green as u32 + 114 * blue as u32) / 1000) as u8
+}
+
+/// The colors cells and grid lines are drawn with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    /// The color of each state of the rule.
+    pub states: Vec<Rgb>,
+    pub grid: Rgb,
+}
+
+impl Palette {
+    /// A palette from `0xRRGGBB` colors for dead cells, live cells and grid
+    /// lines, in that order. Colors left out are the canvas's, and dying
+    /// states fade from the live to the dead color, as on the canvas.
+    pub fn new(rule: &Rule, colors: &[u32]) -> Palette {
+        let color = |idx: usize, default: Rgb| colors.get(idx).map_or(default, |&color| rgb(color));
+        let (dead, alive) = (color(0, DEAD), color(1, ALIVE));
+        let states = (0..rule.states())
+            .map(|state| {
+                let shade = rule.shade(Cell::new(state));
+                let mut mixed = [0; 3];
+                for (mixed, (&dead, &alive)) in mixed.iter_mut().zip(dead.iter().zip(&alive)) {
+                    *mixed = (dead as f32 + (alive as f32 - dead as f32) * shade).round() as u8;
+                }
+                mixed
+            })
+            .collect();
+        Palette {
+            states,
+            grid: color(2, GRID),
+        }
+    }
+
+    pub fn color(&self, cell: Cell) -> Rgb {
+        self.states[cell.state() as usize]
+    }
+}
+
+/// A picture, row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    /// Draw `width` by `height` cells as squares of `cell_px` pixels. With
+    /// `grid`, the squares are separated and surrounded by lines one pixel
+    /// wide.
+    pub fn render(width: u32, height: u32, cells: &[Cell], cell_px: u32, grid: bool, palette: &Palette) -> Image {
+        let line = grid as u32;
+        let (image_width, image_height) = (width * (cell_px + line) + line, height * (cell_px + line) + line);
+        let mut pixels = vec![palette.grid; (image_width * image_height) as usize];
+        for row in 0..height {
+            for col in 0..width {
+                let color = palette.color(cells[(row * width + col) as usize]);
+                let (top, left) = (row * (cell_px + line) + line, col * (cell_px + line) + line);
+                for y in top..top + cell_px {
+                    let start = (y * image_width + left) as usize;
+                    pixels[start..start + cell_px as usize].iter_mut().for_each(|pixel| *pixel = color);
+                }
+            }
+        }
+        Image {
+            width: image_width,
+            height: image_height,
+            pixels,
+        }
+    }
+
+    /// The state of the cell for each pixel: dead below a luminance of
+    /// `threshold`, and otherwise from live for the brightest pixels down to
+    /// the oldest dying state of `rule` for the darkest.
+    pub fn cells(&self, rule: &Rule, threshold: u8) -> Vec<Cell> {
+        let dying = rule.states() as u32 - 1;
+        self.pixels
+            .iter()
+            .map(|&pixel| match luminance(pixel) {
+                luminance if luminance < threshold => Cell::DEAD,
+                luminance => {
+                    let darkness = (255 - luminance as u32) * dying / (256 - threshold as u32);
+                    Cell::new(1 + darkness as u8)
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette() {
+        let palette = Palette::new(&Rule::conway(), &[]);
+        assert_eq!(palette.states, vec![DEAD, ALIVE]);
+        let palette = Palette::new(&"B2/S/C3".parse().unwrap(), &[0x000000, 0xffffff]);
+        assert_eq!(palette.states, vec![[0, 0, 0], [255, 255, 255], [128, 128, 128]]);
+        assert_eq!(palette.grid, GRID);
+        assert_eq!(Palette::new(&Rule::conway(), &[0, 0, 0x123456]).grid, [0x12, 0x34, 0x56]);
+    }
+
+    #[test]
+    fn test_render() {
+        let palette = Palette::new(&Rule::conway(), &[0x000000, 0xffffff, 0x808080]);
+        let cells = [Cell::ALIVE, Cell::DEAD];
+        let image = Image::render(2, 1, &cells, 2, false, &palette);
+        assert_eq!((image.width, image.height), (4, 2));
+        let lums: Vec<u8> = image.pixels.iter().map(|&pixel| luminance(pixel)).collect();
+        assert_eq!(lums, vec![255, 255, 0, 0, 255, 255, 0, 0]);
+
+        let image = Image::render(2, 1, &cells, 1, true, &palette);
+        assert_eq!((image.width, image.height), (5, 3));
+        let lums: Vec<u8> = image.pixels.iter().map(|&pixel| luminance(pixel)).collect();
+        assert_eq!(lums, vec![128, 128, 128, 128, 128, 128, 255, 128, 0, 128, 128, 128, 128, 128, 128]);
+
+        let rule: Rule = "B2/S/C3".parse().unwrap();
+        let image = Image {
+            width: 4,
+            height: 1,
+            pixels: vec![[0, 0, 0], [99, 99, 99], [150, 150, 150], [255, 255, 255]],
+        };
+        assert_eq!(image.cells(&rule, 100), vec![Cell::DEAD, Cell::DEAD, Cell::new(2), Cell::ALIVE]);
+    }
+}
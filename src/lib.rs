@@ -1,11 +1,64 @@
 mod utils;
 mod render;
-
+mod algorithm;
+mod animation;
+mod apgcode;
+mod bitpacked;
+mod census;
+mod cycle;
+mod hashlife;
+mod history;
+mod image;
+mod library;
+mod life106;
+mod ltl;
+mod macrocell;
+mod object;
+mod plane;
+mod paste;
+mod plaintext;
+mod rle;
+mod rule;
+mod snapshot;
+mod statistics;
+mod svg;
+mod tiles;
+mod topology;
+mod transform;
+mod translation;
+
+pub use algorithm::Algorithm;
+pub use animation::Animation;
+pub use bitpacked::BitGrid;
+pub use census::{Census, CensusEntry};
+pub use cycle::{CycleDetector, Stabilization};
+pub use hashlife::HashLife;
+pub use image::{Image, Palette};
+pub use library::{Category, Pattern, PatternLibrary};
+pub use ltl::{LargerThanLife, Neighborhood};
+pub use macrocell::Macrocell;
+pub use plane::{Plane, SparsePlane};
+pub use paste::PasteMode;
+pub use plaintext::Plaintext;
+pub use rle::Rle;
+pub use rule::Rule;
+pub use tiles::DirtyTiles;
+pub use topology::{Edges, Topology};
+pub use transform::Transform;
+pub use translation::Translation;
+
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
 use std::vec;
 
 use wasm_bindgen::prelude::*;
 use js_sys::Math;
-use web_sys::{console, WebGlProgram};
+use web_sys::WebGlProgram;
+
+use history::{Change, Entry, History};
+use statistics::Statistics;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -14,28 +67,31 @@ use web_sys::{console, WebGlProgram};
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 #[cfg(not(test))]
+#[allow(unused_macros)]
 macro_rules! log {
     ( $( $t:tt )* ) => {
-        console::log_1(&format!( $( $t )* ).into());
+        web_sys::console::log_1(&format!( $( $t )* ).into());
     }
 }
 
 #[cfg(test)]
+#[allow(unused_macros)]
 macro_rules! log {
     ( $( $t:tt )* ) => {
         print!("[TEST LOG] ");
-        &println!( $( $t )* );
+        println!( $( $t )* );
     }
 }
 
 pub struct Timer<'a> {
+    #[cfg_attr(test, allow(dead_code))]
     name: &'a str,
 }
 
 impl<'a> Timer<'a> {
     pub fn new(name: &'a str) -> Timer<'a> {
         #[cfg(not(test))]
-        console::time_with_label(name);
+        web_sys::console::time_with_label(name);
         Timer { name }
     }
 }
@@ -43,113 +99,40 @@ impl<'a> Timer<'a> {
 impl<'a> Drop for Timer<'a> {
     fn drop(&mut self) {
         #[cfg(not(test))]
-        console::time_end_with_label(self.name);
+        web_sys::console::time_end_with_label(self.name);
     }
 }
 
-#[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum Cell {
-    Dead = 0,
-    Alive = 1,
-}
+/// The state of a cell. Life-like rules only use `Cell::DEAD` and
+/// `Cell::ALIVE`; Generations rules age live cells through the dying states
+/// `2..states` before they die.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cell(u8);
 
 impl Cell {
-    fn toggle(&mut self) {
-        *self = match *self {
-            Cell::Dead => Cell::Alive,
-            Cell::Alive => Cell::Dead,
-        };
+    pub const DEAD: Cell = Cell(0);
+    pub const ALIVE: Cell = Cell(1);
+
+    pub fn new(state: u8) -> Cell {
+        Cell(state)
     }
-}
 
-pub struct Population {
-    height: u32,
-    width: u32,
-    cells: Vec<(u32, u32)>,
-}
+    pub fn state(self) -> u8 {
+        self.0
+    }
 
-impl Population {
-    pub fn new(name: String) -> Population {
-        let (cells, height, width) = match name.as_str() {
-            "block" => (vec![
-                (0, 0), (0,1),
-                (1, 0), (1, 1)
-            ], 2, 2),
-            "blinker" => (vec![
-                (0, 0), (0, 1), (0, 2)
-            ], 1, 3),
-            "toad" => (vec![
-                        (0, 1), (0, 2), (0,3),
-                (1, 0), (1, 1), (1, 2)
-            ], 2, 4),
-            "beacon" => (vec![
-                (0, 0), (0, 1),
-                (1, 0),
-                                (2, 3),
-                        (3, 2), (3, 3)
-            ], 4, 4),
-            "pulsar" => (vec![
-                                (0, 2), (0, 3), (0, 4),                         (0, 8), (0, 9), (0, 10),
-                (2, 0),                                 (2, 5),         (2, 7),                                 (2, 12),
-                (3 ,0),                                 (3, 5),         (3, 7),                                 (3, 12),
-                (4, 0),                                 (4, 5),         (4, 7),                                 (4, 12),
-                                (5, 2), (5, 3), (5, 4),                         (5, 8), (5, 9), (5, 10),
-                                (7, 2), (7, 3), (7, 4),                         (7, 8), (7, 9), (7, 10),
-                (8, 0),                                 (8, 5),         (8, 7),                                 (8, 12),
-                (9, 0),                                 (9, 5),         (9, 7),                                 (9, 12),
-                (10,0),                                 (10,5),         (10,7),                                 (10,12),
-                                (12,2), (12,3), (12,4),                         (12,8), (12,9), (12,10)
-            ], 13, 13),
-            "i-column" => (vec![
-                (0, 0), (0, 1), (0, 2),
-                        (1, 1),
-                        (2, 1),
-                (3, 0), (3, 1), (3, 2),
-
-                (5, 0), (5, 1), (5, 2),
-                (6, 0), (6, 1), (6, 2),
-
-                (8, 0), (8, 1), (8, 2),
-                        (9, 1),
-                        (10,1),
-                (11,0), (11,1), (11,2)
-            ], 12, 3),
-            "glider" => (vec![
-                        (0, 1),
-                                (1, 2),
-                (2, 0), (2, 1), (2, 2)
-            ], 3, 3),
-            "lwss" => (vec![
-                        (0, 1), (0, 2), (0, 3), (0, 4),
-                (1, 0),                         (1, 4),
-                                                (2, 4),
-                (3, 0),                 (3, 3)
-            ], 4, 5),
-            "mwss" => (vec![
-                        (0, 1), (0, 2), (0, 3), (0, 4), (0, 5),
-                (1, 0),                                 (1, 5),
-                                                        (2, 5),
-                (3, 0),                         (3, 4),
-                                (4, 2)
-            ], 5, 6),
-            "hwss" => (vec![
-                        (0, 1), (0, 2), (0, 3), (0, 4), (0, 5), (0, 6),
-                (1, 0),                                         (1, 6),
-                                                                (2, 6),
-                (3, 0),                                 (3, 5),
-                                (4, 2), (4, 3)
-            ], 5, 7),
-            _ => (vec![
-                (0, 0), (0,1),
-                (1, 0), (1, 1)
-            ], 2, 2),
+    /// Only cells in state 1 count as live neighbors; dying cells do not.
+    pub fn is_alive(self) -> bool {
+        self == Cell::ALIVE
+    }
+
+    fn toggle(&mut self) {
+        *self = if *self == Cell::DEAD {
+            Cell::ALIVE
+        } else {
+            Cell::DEAD
         };
-        Population {
-            height,
-            width,
-            cells
-        }
     }
 }
 
@@ -161,93 +144,612 @@ pub struct Universe {
     cells: [Vec<Cell>; 2],
     cells_idx: usize,
     next_cells_idx: usize,
+    rule: Rule,
+    topology: Topology,
+    /// The cells of an unbounded universe. The cell buffers then hold the
+    /// viewport onto the plane whose top left cell is at `viewport`.
+    plane: Option<Plane>,
+    viewport: (i64, i64),
+    algorithm: Algorithm,
+    /// The cells packed one bit each for the bit-packed algorithm, kept in
+    /// step with the cell buffer once created. Generations only advance the
+    /// packed cells, which are unpacked when the cell buffer is read.
+    packed: Option<BitGrid>,
+    /// Whether the packed cells are generations ahead of the cell buffer.
+    packed_ahead: bool,
+    /// The tiles of the cell buffer that changed in the last generation or
+    /// were edited since.
+    dirty: DirtyTiles,
+    /// The memory limit of the HashLife node cache, in megabytes.
+    hashlife_memory: u32,
+    /// The patterns `seed_population` can seed by name.
+    library: PatternLibrary,
+    statistics: Statistics,
+    /// The edits, resizes and generations that can be undone and redone.
+    history: History,
     cell_program: Option<WebGlProgram>,
     grid_program: Option<WebGlProgram>,
 }
 
+/// The state of a universe saved by `Universe::save`.
+struct Saved {
+    cells: Vec<Cell>,
+    plane: Option<Plane>,
+    statistics: Statistics,
+}
+
 impl Universe {
     fn get_index(&self, row: u32, col: u32) -> usize {
         (row * self.width + col) as usize
     }
 
-    fn live_neighbor_count(&self, row: u32, col: u32) -> u8 {
-        let mut count = 0;
+    /// The cell at `row` and `col`, which may lie outside of the universe.
+    /// The topology decides which cell such positions refer to, if any.
+    fn cell_at(&self, row: i64, col: i64) -> Cell {
+        match (self.topology.map(self.width, self.height, row, col), &self.packed) {
+            (Some((row, col)), Some(packed)) if self.packed_ahead => match packed.get(row, col) {
+                true => Cell::ALIVE,
+                false => Cell::DEAD,
+            },
+            (Some((row, col)), _) => self.cells[self.cells_idx][self.get_index(row, col)],
+            (None, _) => Cell::DEAD,
+        }
+    }
 
-        let north = if row == 0 {
-            self.height - 1
-        } else {
-            row - 1
-        };
+    /// The cells of the cell buffer, unpacked afresh when the packed cells
+    /// are ahead of it.
+    fn current_cells(&self) -> Cow<'_, [Cell]> {
+        match &self.packed {
+            Some(packed) if self.packed_ahead => {
+                let mut cells = vec![Cell::DEAD; self.cells[self.cells_idx].len()];
+                packed.write_cells(&mut cells);
+                Cow::Owned(cells)
+            }
+            _ => Cow::Borrowed(&self.cells[self.cells_idx]),
+        }
+    }
+
+    /// Bring the cell buffer up to date with the packed cells, marking the
+    /// tiles that changed since it last was.
+    fn unpack(&mut self) {
+        if let Some(packed) = self.packed.as_ref().filter(|_| self.packed_ahead) {
+            packed.write_cells(&mut self.cells[self.next_cells_idx]);
+            let (old, new) = (&self.cells[self.cells_idx], &self.cells[self.next_cells_idx]);
+            self.dirty = DirtyTiles::diff(self.width, self.height, old, new);
+            self.cells_idx = (self.cells_idx + 1) & 1;
+            self.next_cells_idx = (self.next_cells_idx + 1) & 1;
+        }
+        self.packed_ahead = false;
+    }
 
-        let south = if row == self.height - 1 {
-            0
+    /// Set the cell at `row` and `col`, mapped into the universe by its
+    /// topology. Positions that refer to no cell are ignored, except on an
+    /// unbounded plane where they are relative to the viewport.
+    fn set_cell_at(&mut self, row: i64, col: i64, cell: Cell) {
+        self.unpack();
+        if let Some(plane) = &mut self.plane {
+            let (row, col) = (self.viewport.0 + row, self.viewport.1 + col);
+            self.history.record(row, col, plane.get(row, col), cell);
+            plane.set(row, col, cell);
+        }
+        if let Some((row, col)) = self.topology.map(self.width, self.height, row, col) {
+            let idx = self.get_index(row, col);
+            if self.plane.is_none() {
+                self.history.record(row as i64, col as i64, self.cells[self.cells_idx][idx], cell);
+            }
+            self.statistics.edit(self.cells[self.cells_idx][idx], cell);
+            self.cells[self.cells_idx][idx] = cell;
+            self.dirty.mark(row, col);
+            if let Some(packed) = &mut self.packed {
+                packed.set(row, col, cell.is_alive());
+            }
+        }
+    }
+
+    /// The neighborhood configuration index of a cell: bit `r * 3 + c` is
+    /// set when the cell at row `r` and column `c` of the 3x3 block centered
+    /// on it is alive.
+    fn neighborhood_index(&self, row: u32, col: u32) -> usize {
+        let mut index = 0;
+        if row > 0 && row + 1 < self.height && col > 0 && col + 1 < self.width {
+            let cells = &self.cells[self.cells_idx];
+            for (bit, (row, col)) in (row - 1..=row + 1)
+                .flat_map(|row| (col - 1..=col + 1).map(move |col| (row, col)))
+                .enumerate()
+            {
+                index |= (cells[self.get_index(row, col)].is_alive() as usize) << bit;
+            }
         } else {
-            row + 1
+            let (row, col) = (row as i64, col as i64);
+            for (bit, (row, col)) in (row - 1..=row + 1)
+                .flat_map(|row| (col - 1..=col + 1).map(move |col| (row, col)))
+                .enumerate()
+            {
+                index |= (self.cell_at(row, col).is_alive() as usize) << bit;
+            }
+        }
+
+        index
+    }
+
+    /// Get the dead and alive values of the entire universe.
+    pub fn get_cells(&mut self) -> &[Cell] {
+        self.unpack();
+        &self.cells[self.cells_idx]
+    }
+
+    /// Set cells to be alive in a universe by passing the row and column
+    /// of each cell as an array. Cells beyond the edges are placed according
+    /// to the universe's topology.
+    pub fn set_cells(&mut self, cells: &[(u32, u32)]) {
+        for (row, col) in cells.iter().cloned() {
+            self.set_cell_at(row as i64, col as i64, Cell::ALIVE);
+        }
+        self.history.commit();
+    }
+
+    /// The cell `set_cell_at` sets.
+    fn get_cell_at(&self, row: i64, col: i64) -> Cell {
+        match &self.plane {
+            Some(plane) => plane.get(self.viewport.0 + row, self.viewport.1 + col),
+            None => self.cell_at(row, col),
+        }
+    }
+
+    /// Paste `cells`, given by row, column and state within a `height` by
+    /// `width` box whose top left cell is at `row` and `col`, combining them
+    /// with the cells under the box by `mode`. Only the modes that change
+    /// the cells the pattern leaves dead go through the whole box, which may
+    /// then hold at most `paste::MAX_BOX_CELLS` cells.
+    fn paste(
+        &mut self,
+        row: i64,
+        col: i64,
+        height: u32,
+        width: u32,
+        cells: &[(u32, u32, Cell)],
+        mode: PasteMode,
+    ) -> Result<(), String> {
+        let paste_cell = |universe: &mut Universe, cell_row: u32, cell_col: u32, pasted: Cell| {
+            let (row, col) = (row + cell_row as i64, col + cell_col as i64);
+            let existing = universe.get_cell_at(row, col);
+            let cell = mode.combine(existing, pasted);
+            if cell != existing {
+                universe.set_cell_at(row, col, cell);
+            }
         };
+        if mode.keeps_dead() {
+            for &(cell_row, cell_col, cell) in cells {
+                paste_cell(self, cell_row, cell_col, cell);
+            }
+            return Ok(());
+        }
 
-        let west = if col == 0 {
-            self.width - 1
-        } else {
-            col - 1
+        let size = height as u64 * width as u64;
+        if size > paste::MAX_BOX_CELLS {
+            return Err(format!(
+                "cannot paste a {} by {} box in {} mode, which is more than {} cells",
+                width,
+                height,
+                mode,
+                paste::MAX_BOX_CELLS
+            ));
+        }
+        let mut pasted = vec![Cell::DEAD; size as usize];
+        for &(cell_row, cell_col, cell) in cells {
+            pasted[cell_row as usize * width as usize + cell_col as usize] = cell;
+        }
+        for cell_row in 0..height {
+            for cell_col in 0..width {
+                let cell = pasted[cell_row as usize * width as usize + cell_col as usize];
+                paste_cell(self, cell_row, cell_col, cell);
+            }
+        }
+        Ok(())
+    }
+
+    /// Paste a pattern from the library centered on `row` and `col`.
+    fn seed(&mut self, row: u32, col: u32, name: &str, transform: Transform, mode: &str) -> Result<(), String> {
+        let mode: PasteMode = mode.parse()?;
+        let pop = self.library.get(name)?;
+        let (height, width) = transform.size(pop.height(), pop.width());
+        let row = row as i64 - (height / 2) as i64;
+        let col = col as i64 - (width / 2) as i64;
+        // log!("Universe::seed_population() adjusted row: {}, col: {}", row, col);
+        let cells: Vec<(u32, u32, Cell)> = pop
+            .live_cells()
+            .iter()
+            .map(|&(cell_row, cell_col)| {
+                let (cell_row, cell_col) = transform.apply(cell_row, cell_col, pop.height(), pop.width());
+                (cell_row, cell_col, Cell::ALIVE)
+            })
+            .collect();
+        self.paste(row, col, height, width, &cells, mode)?;
+        self.history.commit();
+        Ok(())
+    }
+
+    /// Set the cell at `row` and `col` of the cell buffer, or of an
+    /// unbounded plane, as positions are recorded in the history.
+    fn restore_cell(&mut self, row: i64, col: i64, cell: Cell) {
+        match self.plane {
+            Some(_) => self.set_cell_at(row - self.viewport.0, col - self.viewport.1, cell),
+            None => self.set_cell_at(row, col, cell),
+        }
+    }
+
+    /// The cells to compare a generation with to record it in the history,
+    /// by their positions in the cell buffer or plane, or `None` when
+    /// generations are not recorded. They never are on a HashLife plane,
+    /// whose cells may be far too many to list every step.
+    fn generation_start(&self) -> Option<Vec<(i64, i64, Cell)>> {
+        match self.plane {
+            Some(Plane::HashLife(_)) => None,
+            _ if self.history.is_recording_generations() => Some(self.occupied_cells()),
+            _ => None,
+        }
+    }
+
+    /// Record the cells that changed since `generation_start`. Without them
+    /// edits and resizes can still be undone, but not the generations
+    /// before.
+    fn record_generation(&mut self, before: Option<Vec<(i64, i64, Cell)>>) {
+        let before = match before {
+            Some(before) => before,
+            None => return self.history.skip_generation(),
         };
+        let mut old: HashMap<(i64, i64), Cell> = before.into_iter().map(|(row, col, cell)| ((row, col), cell)).collect();
+        let mut changes = Vec::new();
+        for (row, col, new) in self.occupied_cells() {
+            let old = old.remove(&(row, col)).unwrap_or(Cell::DEAD);
+            if old != new {
+                changes.push(Change { row, col, old, new });
+            }
+        }
+        changes.extend(old.into_iter().map(|((row, col), old)| Change {
+            row,
+            col,
+            old,
+            new: Cell::DEAD,
+        }));
+        self.history.push(Entry::Generation(changes));
+    }
 
-        let east = if col == self.width - 1 {
-            0
+    /// Undo `entry`, or redo it when `redo` is set.
+    fn apply(&mut self, entry: &Entry, redo: bool) {
+        match entry {
+            // A cell may change more than once in a step, so the changes
+            // are undone last to first.
+            Entry::Edit(changes) | Entry::Generation(changes) if redo => {
+                for change in changes {
+                    self.restore_cell(change.row, change.col, change.new);
+                }
+            }
+            Entry::Edit(changes) | Entry::Generation(changes) => {
+                for change in changes.iter().rev() {
+                    self.restore_cell(change.row, change.col, change.old);
+                }
+            }
+            Entry::Resize { old, new, cleared } => {
+                let (width, height, size) = if redo { *new } else { *old };
+                self.size = size;
+                self.resize_cells(width, height);
+                if !redo {
+                    for change in cleared {
+                        self.restore_cell(change.row, change.col, change.old);
+                    }
+                }
+            }
+        }
+        self.history.discard();
+    }
+
+    /// Kill the dying cells in states the rule does not have, left by a rule
+    /// with more states. The history is cleared, as undoing could bring
+    /// them back.
+    fn kill_extra_states(&mut self) {
+        let states = self.rule.states();
+        let extra = |cell: Cell| cell.state() >= states;
+        if let Some(plane) = &mut self.plane {
+            for (row, col, cell) in plane.cells() {
+                if extra(cell) {
+                    plane.set(row, col, Cell::DEAD);
+                }
+            }
+        }
+        let mut killed = false;
+        for cells in self.cells.iter_mut() {
+            for cell in cells.iter_mut().filter(|cell| extra(**cell)) {
+                *cell = Cell::DEAD;
+                killed = true;
+            }
+        }
+        if killed {
+            self.history.clear();
+        }
+    }
+
+    /// Kill every cell.
+    fn clear_all(&mut self) {
+        if let Some(plane) = &mut self.plane {
+            for (row, col, cell) in plane.cells() {
+                self.history.record(row, col, cell, Cell::DEAD);
+            }
+            plane.clear();
+        }
+        self.clear_cells(0, 0, self.width, self.height);
+    }
+
+    fn clear_cells(&mut self, row: i64, col: i64, h_size: u32, v_size: u32) {
+        for row in row..row + v_size as i64 {
+            for col in col..col + h_size as i64 {
+                self.set_cell_at(row, col, Cell::DEAD);
+            }
+        }
+    }
+
+    /// Replace the cell buffers with dead cells for a `width` by `height`
+    /// universe, resizing the canvas when there is one.
+    fn resize_cells(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.cells[0] = (0..width * height).map(|_i| Cell::DEAD).collect();
+        self.cells[1] = (0..width * height).map(|_i| Cell::DEAD).collect();
+        self.packed = None;
+        self.packed_ahead = false;
+        self.dirty = DirtyTiles::new(self.width, self.height);
+        self.statistics.invalidate();
+        self.sync_viewport();
+        if self.cell_program.is_some() {
+            let size = self.size as u32;
+            render::resize_canvas((size + 1) * width + 1, (size + 1) * height + 1)
+                .unwrap();
+        }
+    }
+
+    /// The position and state of every cell that is not dead: plane
+    /// coordinates on an unbounded plane, otherwise the row and column in
+    /// the universe.
+    fn occupied_cells(&self) -> Vec<(i64, i64, Cell)> {
+        match &self.plane {
+            Some(plane) => plane.cells(),
+            None => {
+                let cells = self.current_cells();
+                (0..self.height)
+                    .flat_map(|row| (0..self.width).map(move |col| (row, col)))
+                    .map(|(row, col)| (row as i64, col as i64, cells[self.get_index(row, col)]))
+                    .filter(|&(_, _, cell)| cell != Cell::DEAD)
+                    .collect()
+            }
+        }
+    }
+
+    /// Check that `rule` can run on `topology` with the current algorithm,
+    /// before either is changed.
+    fn check_topology(&self, rule: &Rule, topology: Topology) -> Result<(), String> {
+        if topology == Topology::Unbounded && rule.births_from_nothing() {
+            return Err(format!(
+                "rule \"{}\" gives birth in empty space and cannot run on an unbounded plane",
+                rule
+            ));
+        }
+        self.algorithm.check(rule, topology)
+    }
+
+    /// Switch to a topology that `check_topology` accepted; see
+    /// `set_topology`.
+    fn apply_topology(&mut self, topology: Topology, width: u32, height: u32) {
+        if topology == Topology::Unbounded {
+            if self.plane.is_none() {
+                let mut plane = self.new_plane();
+                for row in 0..self.height {
+                    for col in 0..self.width {
+                        let cell = self.cells[self.cells_idx][self.get_index(row, col)];
+                        plane.set(self.viewport.0 + row as i64, self.viewport.1 + col as i64, cell);
+                    }
+                }
+                self.plane = Some(plane);
+            }
         } else {
-            col + 1
-        };
+            self.plane = None;
+            if width != self.width || height != self.height {
+                self.resize_cells(width, height);
+            }
+        }
+        self.topology = topology;
+        self.dirty = DirtyTiles::new(self.width, self.height);
+        self.statistics.invalidate();
+        self.history.clear();
+    }
 
-        let nw = self.get_index(north, west);
-        count += self.cells[self.cells_idx][nw] as u8;
+    /// The cell that a position next to the universe's cells refers to under
+    /// the topology, or `None` when it is outside the universe. Positions on
+    /// an unbounded plane are their own cells.
+    fn wrap(&self, row: i64, col: i64) -> Option<(i64, i64)> {
+        match self.plane {
+            Some(_) => Some((row, col)),
+            None => self
+                .topology
+                .map(self.width, self.height, row, col)
+                .map(|(row, col)| (row as i64, col as i64)),
+        }
+    }
 
-        let n = self.get_index(north, col);
-        count += self.cells[self.cells_idx][n] as u8;
+    /// The hash of every cell of the universe: the cell buffer, or the
+    /// cells of an unbounded plane.
+    fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        match &self.plane {
+            Some(plane) => {
+                let mut cells = plane.cells();
+                cells.sort_unstable();
+                cells.hash(&mut hasher);
+            }
+            // The bit-packed algorithm hashes the packed cells, so they need
+            // not be unpacked every generation.
+            None => match &self.packed {
+                Some(packed) => packed.hash(&mut hasher),
+                None if self.algorithm == Algorithm::BitPacked => {
+                    BitGrid::from_cells(self.width, self.height, &self.cells[self.cells_idx]).hash(&mut hasher)
+                }
+                None => self.cells[self.cells_idx].hash(&mut hasher),
+            },
+        }
+        hasher.finish()
+    }
 
-        let ne = self.get_index(north, east);
-        count += self.cells[self.cells_idx][ne] as u8;
+    /// The row and column of every live cell, sorted, as they are written by
+    /// the two-state pattern formats.
+    fn live_cells(&self) -> Vec<(i64, i64)> {
+        let mut cells: Vec<(i64, i64)> = self
+            .occupied_cells()
+            .into_iter()
+            .filter(|&(_, _, cell)| cell.is_alive())
+            .map(|(row, col, _)| (row, col))
+            .collect();
+        cells.sort_unstable();
+        cells
+    }
 
-        let w = self.get_index(row, west);
-        count += self.cells[self.cells_idx][w] as u8;
+    /// The rule with the topology, unless it is unbounded, as written in
+    /// pattern files.
+    fn rule_spec(&self) -> String {
+        match self.topology {
+            Topology::Unbounded => self.rule.to_string(),
+            topology => format!("{}:{}", self.rule, topology.spec(self.width, self.height)),
+        }
+    }
 
-        let e = self.get_index(row, east);
-        count += self.cells[self.cells_idx][e] as u8;
+    /// Copy the viewport of an unbounded plane into the cell buffer.
+    fn sync_viewport(&mut self) {
+        if let Some(plane) = &self.plane {
+            let (row, col) = self.viewport;
+            let cells = plane.region(row, col, self.height, self.width);
+            let (width, height) = (self.width, self.height);
+            self.dirty = DirtyTiles::diff(width, height, &self.cells[self.cells_idx], &cells);
+            self.cells[self.cells_idx] = cells;
+        }
+    }
 
-        let sw = self.get_index(south, west);
-        count += self.cells[self.cells_idx][sw] as u8;
+    /// Advance the universe `generations` generations with the bit-packed
+    /// algorithm, counting births and deaths in the packed words. The cell
+    /// buffer is left to be unpacked when it is next read.
+    fn tick_packed(&mut self, generations: u64) {
+        let (birth, survival) = self
+            .rule
+            .totalistic()
+            .expect("the bit-packed algorithm only runs totalistic rules");
+        let wrap = self.topology == Topology::Torus;
+        if self.packed.is_none() {
+            let cells = &self.cells[self.cells_idx];
+            self.packed = Some(BitGrid::from_cells(self.width, self.height, cells));
+        }
+        let packed = self.packed.as_mut().unwrap();
+        let (births, deaths) = if generations == 1 {
+            packed.tick(birth, survival, wrap)
+        } else {
+            let old = packed.clone();
+            for _ in 0..generations {
+                packed.tick(birth, survival, wrap);
+            }
+            packed.changes(&old)
+        };
+        self.packed_ahead = true;
+        self.record_step(births, deaths);
+    }
 
-        let s = self.get_index(south, col);
-        count += self.cells[self.cells_idx][s] as u8;
+    /// Advance an unbounded plane `generations` generations. Births and
+    /// deaths are counted in the viewport.
+    fn step_plane(&mut self, generations: u64) {
+        let old = self.cells[self.cells_idx].clone();
+        if let Some(plane) = &mut self.plane {
+            plane.step(&self.rule, generations);
+        }
+        self.sync_viewport();
+        let (births, deaths) = statistics::changes(&old, &self.cells[self.cells_idx]);
+        self.record_step(births, deaths);
+    }
 
-        let se = self.get_index(south, east);
-        count += self.cells[self.cells_idx][se] as u8;
+    /// Record the births and deaths of a step in the statistics, with the
+    /// population it left.
+    fn record_step(&mut self, births: u32, deaths: u32) {
+        let population = match (&self.plane, self.statistics.population()) {
+            (Some(plane), _) => plane.population(),
+            (None, Some(population)) => population + births as u64 - deaths as u64,
+            (None, None) => statistics::count(&self.current_cells()),
+        };
+        self.statistics.record(births, deaths, population);
+    }
 
-        count
+    /// The cells, sorted, to compare states of the universe by.
+    fn sorted_cells(&self) -> Vec<(i64, i64, Cell)> {
+        let mut cells = self.occupied_cells();
+        cells.sort_unstable();
+        cells
     }
 
-    /// Get the dead and alive values of the entire universe.
-    pub fn get_cells(&self) -> &[Cell] {
-        &self.cells[self.cells_idx]
+    /// The cells and statistics, to go back to with `restore` after running
+    /// the universe ahead.
+    fn save(&self) -> Saved {
+        Saved {
+            cells: self.current_cells().into_owned(),
+            plane: self.plane.clone(),
+            statistics: self.statistics.clone(),
+        }
     }
 
-    /// Set cells to be alive in a universe by passing the row and column
-    /// of each cell as an array.
-    pub fn set_cells(&mut self, cells: &[(u32, u32)]) {
-        for (row, col) in cells.iter().cloned() {
-            let idx = self.get_index(row % self.height, col % self.width);
-            self.cells[self.cells_idx][idx] = Cell::Alive;
+    fn restore(&mut self, saved: Saved) {
+        self.cells[self.cells_idx] = saved.cells;
+        self.plane = saved.plane;
+        self.statistics = saved.statistics;
+        self.packed = None;
+        self.packed_ahead = false;
+        self.dirty = DirtyTiles::new(self.width, self.height);
+    }
+
+    /// Run the universe from the generation a search for a cycle of
+    /// `period` started at, up to the first of `starts` whose cells come
+    /// back a period later. Only the cells of `starts` are kept, until they
+    /// are compared.
+    fn first_repeat(&mut self, starts: &[u32], period: u32) -> u32 {
+        let mut kept = VecDeque::new();
+        let mut starts = starts.iter().peekable();
+        let mut generation = 0;
+        loop {
+            if kept.front().map(|&(start, _)| start + period) == Some(generation) {
+                let (start, cells) = kept.pop_front().unwrap();
+                if cells == self.sorted_cells() {
+                    return start;
+                }
+            }
+            if starts.peek() == Some(&&generation) {
+                starts.next();
+                kept.push_back((generation, self.sorted_cells()));
+            }
+            self.advance();
+            generation += 1;
         }
     }
 
-    fn clear_cells(&mut self, row: u32, col: u32, h_size: u32, v_size: u32) {
-        for row in row..row + v_size {
-            for col in col..col + h_size {
-                let idx = self.get_index(row % self.height, col % self.width);
-                self.cells[self.cells_idx][idx] = Cell::Dead;
+    /// An empty unbounded plane for the current algorithm.
+    fn new_plane(&self) -> Plane {
+        match self.algorithm {
+            Algorithm::Generic | Algorithm::BitPacked => Plane::Sparse(SparsePlane::new()),
+            Algorithm::HashLife => {
+                let mut hashlife = HashLife::new(&self.rule);
+                hashlife.set_memory_limit(self.hashlife_memory);
+                Plane::HashLife(Box::new(hashlife))
             }
         }
     }
 }
 
+impl Default for Universe {
+    fn default() -> Universe {
+        Universe::new()
+    }
+}
+
 /// Public methods, exported to JavaScript.
 #[wasm_bindgen]
 impl Universe {
@@ -258,16 +760,14 @@ impl Universe {
         let size = 8;
 
         let (cell_program, grid_program) = if !cfg!(test) {
-            match render::start((size as u32 + 1) * width + 1, (size as u32 + 1) * height + 1) {
-                Ok(program_option_tup) => program_option_tup,
-                Err(_) => (None, None),
-            }
+            render::start((size as u32 + 1) * width + 1, (size as u32 + 1) * height + 1)
+                .unwrap_or_default()
         } else {
             (None, None)
         };
 
-        let cells_0: Vec<Cell> = (0..width * height).map(|_i| Cell::Dead).collect();
-        let cells_1: Vec<Cell> = (0..width * height).map(|_i| Cell::Dead).collect();
+        let cells_0: Vec<Cell> = (0..width * height).map(|_i| Cell::DEAD).collect();
+        let cells_1: Vec<Cell> = (0..width * height).map(|_i| Cell::DEAD).collect();
         let cells = [cells_0, cells_1];
         Universe {
             width,
@@ -276,62 +776,254 @@ impl Universe {
             size,
             cells_idx: 0,
             next_cells_idx: 1,
+            rule: Rule::default(),
+            topology: Topology::default(),
+            plane: None,
+            viewport: (0, 0),
+            algorithm: Algorithm::default(),
+            packed: None,
+            packed_ahead: false,
+            dirty: DirtyTiles::new(width, height),
+            hashlife_memory: 256,
+            library: PatternLibrary::new(),
+            statistics: Statistics::new(),
+            history: History::default(),
             cell_program,
             grid_program,
         }
     }
 
     pub fn tick(&mut self) {
+        let before = self.generation_start();
+        self.advance();
+        self.record_generation(before);
+    }
+
+    /// Advance the universe one generation.
+    fn advance(&mut self) {
         // let _timer = Timer::new("Universe::tick()");
 
-        for row in 0..self.height {
-            for col in 0..self.width {
-                let idx = self.get_index(row, col);
+        if self.plane.is_some() {
+            self.step_plane(1);
+            return;
+        }
+
+        if self.algorithm == Algorithm::BitPacked {
+            self.tick_packed(1);
+            return;
+        }
+
+        let (mut births, mut deaths) = (0, 0);
+        if let Some(ltl) = self.rule.larger_than_life() {
+            let counts = ltl.count_neighbors(self.width, self.height, |row, col| {
+                self.cell_at(row, col).is_alive()
+            });
+            let mut dirty = DirtyTiles::clean(self.width, self.height);
+            for (idx, count) in counts.into_iter().enumerate() {
                 let cell = self.cells[self.cells_idx][idx];
-                let live_neighbors = self.live_neighbor_count(row, col);
-                //
-                // log!(
-                //     "cel[{}, {}] is initially {:?} and has {} life neighbors",
-                //     row,
-                //     col,
-                //     cell,
-                //     live_neighbors
-                // );
-
-                let next_cell = match (cell, live_neighbors) {
-                    // Rule 1: Any live cell with fewer than two live neighbours
-                    // dies, as if caused by underpopulation.
-                    (Cell::Alive, x) if x < 2 => {
-                        // log!("cel[{}, {}] died of loneliness", row, col);
-                        Cell::Dead
-                    },
-                    // Rule 2: Any live cell with two or three live neighbours
-                    // lives on to the next generation.
-                    (Cell::Alive, 2) | (Cell::Alive, 3) => Cell::Alive,
-                    // Rule 3: Any live cell with more than three live
-                    // neighbours dies, as if by overpopulation.
-                    (Cell::Alive, x) if x > 3 => {
-                        // log!("cel[{}, {}] died of agoraphobia", row, col);
-                        Cell::Dead
-                    },
-                    // Rule 4: Any dead cell with exactly three live neighbours
-                    // becomes a live cell, as if by reproduction.
-                    (Cell::Dead, 3) => {
-                        // log!("cel[{}, {}] was born", row, col);
-                        Cell::Alive
-                    },
-                    // All other cells remain in the same state.
-                    (otherwise, _) => otherwise,
-                };
-                self.cells[self.next_cells_idx][idx] = next_cell
+                let next_cell = self.rule.next_by_count(cell, count);
+                if next_cell != cell {
+                    dirty.mark(idx as u32 / self.width, idx as u32 % self.width);
+                    if next_cell.is_alive() {
+                        births += 1;
+                    } else if cell.is_alive() {
+                        deaths += 1;
+                    }
+                }
+                self.cells[self.next_cells_idx][idx] = next_cell;
             }
+            self.dirty = dirty;
+        } else {
+            // Only tiles whose neighborhood changed are computed, the rest
+            // are copied over unchanged.
+            let active = self.dirty.active(self.topology);
+            let mut dirty = DirtyTiles::clean(self.width, self.height);
+            for tile_row in 0..self.dirty.rows() {
+                for tile_col in 0..self.dirty.columns() {
+                    let (rows, cols) = self.dirty.cells(tile_row, tile_col);
+                    let compute = active[(tile_row * self.dirty.columns() + tile_col) as usize];
+                    for row in rows {
+                        for col in cols.clone() {
+                            let idx = self.get_index(row, col);
+                            let cell = self.cells[self.cells_idx][idx];
+                            if !compute {
+                                self.cells[self.next_cells_idx][idx] = cell;
+                                continue;
+                            }
+                            let neighborhood = self.neighborhood_index(row, col);
+                            //
+                            // log!(
+                            //     "cel[{}, {}] has neighborhood {:09b}",
+                            //     row,
+                            //     col,
+                            //     neighborhood
+                            // );
+
+                            let next_cell = self.rule.next(cell, neighborhood);
+                            if next_cell != cell {
+                                dirty.mark(row, col);
+                                if next_cell.is_alive() {
+                                    births += 1;
+                                } else if cell.is_alive() {
+                                    deaths += 1;
+                                }
+                            }
+                            self.cells[self.next_cells_idx][idx] = next_cell
+                        }
+                    }
+                }
+            }
+            self.dirty = dirty;
         }
 
         self.cells_idx = (self.cells_idx + 1) & 1;
         self.next_cells_idx = (self.next_cells_idx + 1) & 1;
+        self.record_step(births, deaths);
     }
 
-    pub fn render(&self) {
+    /// Advance the universe `generations` generations. With the HashLife
+    /// algorithm this takes time roughly logarithmic in `generations` for
+    /// regular patterns, so it can look at generation 10^6 and beyond.
+    /// `generations` is a `BigInt` in JavaScript.
+    pub fn step_by(&mut self, generations: u64) {
+        let before = self.generation_start();
+        match self.plane {
+            Some(_) => self.step_plane(generations),
+            None if self.algorithm == Algorithm::BitPacked => self.tick_packed(generations),
+            None => {
+                for _ in 0..generations {
+                    self.advance();
+                }
+            }
+        }
+        self.record_generation(before);
+    }
+
+    /// Run the universe until it repeats a state, for at most `max_gens`
+    /// generations, and return the generation the cycle it settled into
+    /// starts at, counted from now, and its period. States are told apart by
+    /// the hash of their cells, and a repeated hash is only taken for a cycle
+    /// once the cells are seen to come back, so the universe runs two
+    /// periods into the cycle before it is found, and is left there. The run
+    /// is one step in the history.
+    pub fn run_until_stable(&mut self, max_gens: u32) -> Option<Stabilization> {
+        let before = self.generation_start();
+        let initial = self.save();
+        let mut detector = CycleDetector::new();
+        let mut found = None;
+        for generation in 0..=max_gens {
+            if generation > 0 {
+                self.advance();
+            }
+            found = detector.observe(self.state_hash(), || self.sorted_cells());
+            if found.is_some() {
+                break;
+            }
+        }
+        let stabilization = found.map(|period| {
+            // The cells of earlier generations are gone, so the universe is
+            // run again to compare those whose hash comes back.
+            let starts = detector.starts(period);
+            let generation = match starts[..] {
+                [start] => start,
+                _ => {
+                    let end = self.save();
+                    self.restore(initial);
+                    let start = self.first_repeat(&starts, period);
+                    self.restore(end);
+                    start
+                }
+            };
+            Stabilization::new(generation, period)
+        });
+        self.record_generation(before);
+        stabilization
+    }
+
+    /// Find how the current contents recur within `max_period`
+    /// generations: the period, and how far they moved, wrapping around the
+    /// edges of a torus. The universe is left as it was.
+    pub fn detect_translation(&mut self, max_period: u32) -> Result<Translation, String> {
+        let before = self.occupied_cells();
+        if before.is_empty() {
+            return Err(String::from("the universe is empty"));
+        }
+        let not_found = || format!("the contents do not recur within {} generations", max_period);
+        if self.plane.is_some() {
+            return object::evolve(&self.rule, &before, max_period)
+                .map(|evolution| Translation::new(evolution.period, evolution.dy, evolution.dx))
+                .ok_or_else(not_found);
+        }
+        let torus = match self.topology {
+            Topology::Torus => Some((self.height, self.width)),
+            Topology::Plane => None,
+            topology => return Err(format!("translations are not defined on a {}", topology)),
+        };
+
+        let saved = self.save();
+        let mut found = None;
+        for generation in 1..=max_period {
+            self.advance();
+            if let Some((dy, dx)) = translation::shift(&before, &self.occupied_cells(), torus) {
+                found = Some(Translation::new(generation, dy, dx));
+                break;
+            }
+        }
+        self.restore(saved);
+        found.ok_or_else(not_found)
+    }
+
+    /// Select the algorithm used by `tick` and `step_by`: `generic`, which
+    /// runs every rule and topology, `bitpacked`, which needs a torus or a
+    /// bounded plane and a two-state totalistic rule, or `hashlife`, which
+    /// needs an unbounded plane and a two-state rule on the eight nearest
+    /// neighbors. The cells are kept, and are still exported one byte per
+    /// cell whatever the algorithm.
+    pub fn set_algorithm(&mut self, name: &str) -> Result<(), String> {
+        let algorithm: Algorithm = name.parse()?;
+        algorithm.check(&self.rule, self.topology)?;
+        self.unpack();
+        self.algorithm = algorithm;
+        self.packed = None;
+        self.dirty = DirtyTiles::new(self.width, self.height);
+        if let Some(plane) = self.plane.take() {
+            let mut converted = self.new_plane();
+            for (row, col, cell) in plane.cells() {
+                converted.set(row, col, cell);
+            }
+            self.plane = Some(converted);
+        }
+        Ok(())
+    }
+
+    pub fn algorithm(&self) -> String {
+        self.algorithm.to_string()
+    }
+
+    /// Limit the memory used by HashLife's node cache, in megabytes. The
+    /// cache is garbage collected whenever it grows past the limit.
+    pub fn set_hashlife_memory(&mut self, megabytes: u32) {
+        self.hashlife_memory = megabytes;
+        if let Some(Plane::HashLife(hashlife)) = &mut self.plane {
+            hashlife.set_memory_limit(megabytes);
+        }
+    }
+
+    /// The tiles of cells that changed in the last generation or were edited
+    /// since, as the row and column of each tile one after the other. Tiles
+    /// are `tile_size` cells square, the last row and column of tiles being
+    /// cut short by the edges of the universe.
+    pub fn dirty_tiles(&mut self) -> Vec<u32> {
+        self.unpack();
+        self.dirty.positions()
+    }
+
+    pub fn tile_size(&self) -> u32 {
+        tiles::TILE
+    }
+
+    pub fn render(&mut self) {
+        self.unpack();
         let mut vertices: Vec<f32> = vec![];
         let mut grid_vertices: Vec<f32> = vec![];
         let x_pixels = (self.size as u32 + 1) * self.width + 1;
@@ -345,7 +1037,9 @@ impl Universe {
         for x in 0..self.width {
             for y in 0..self.height {
                 let idx = self.get_index(y, x);
-                if self.cells[self.cells_idx][idx] == Cell::Alive {
+                let cell = self.cells[self.cells_idx][idx];
+                if cell != Cell::DEAD {
+                    let shade = self.rule.shade(cell);
                     let cell_x0 = x_start + (x as f32 * (x_grid + x_size)) +  x_grid;
                     let cell_y0 = y_start + (y as f32 * (y_grid + y_size)) +  y_grid;
                     let cell_x1 = cell_x0 + x_size;
@@ -353,21 +1047,27 @@ impl Universe {
                     vertices.push(cell_x0);
                     vertices.push(cell_y0);
                     vertices.push(0.0);
+                    vertices.push(shade);
                     vertices.push(cell_x1);
                     vertices.push(cell_y0);
                     vertices.push(0.0);
+                    vertices.push(shade);
                     vertices.push(cell_x0);
                     vertices.push(cell_y1);
                     vertices.push(0.0);
+                    vertices.push(shade);
                     vertices.push(cell_x1);
                     vertices.push(cell_y1);
                     vertices.push(0.0);
+                    vertices.push(shade);
                     vertices.push(cell_x0);
                     vertices.push(cell_y1);
                     vertices.push(0.0);
+                    vertices.push(shade);
                     vertices.push(cell_x1);
                     vertices.push(cell_y0);
                     vertices.push(0.0);
+                    vertices.push(shade);
                     
                 }
                 if x == 0 {
@@ -404,12 +1104,12 @@ impl Universe {
 
         let cell_program = match &self.cell_program {
             Some(program) => program,
-            None => return (),
+            None => return,
         };
 
         let grid_program = match &self.grid_program {
             Some(program) => program,
-            None => return (),
+            None => return,
         };
 
         render::render(cell_program, vertices, grid_program, grid_vertices).expect("error rendering");
@@ -419,6 +1119,175 @@ impl Universe {
         self.to_string()
     }
 
+    /// A PNG image of the cells in view, each drawn as a square of `cell_px`
+    /// pixels, separated by grid lines with `grid`. `palette` holds
+    /// `0xRRGGBB` colors for dead cells, live cells and grid lines; colors
+    /// left out are the ones the canvas uses.
+    pub fn to_png(&self, cell_px: u32, grid: bool, palette: &[u32]) -> Result<Vec<u8>, String> {
+        if cell_px == 0 {
+            return Err(String::from("cells must be at least one pixel wide"));
+        }
+        let palette = Palette::new(&self.rule, palette);
+        let image = Image::render(self.width, self.height, &self.current_cells(), cell_px, grid, &palette);
+        snapshot::encode(&image)
+    }
+
+    /// An SVG image of the cells in view, laid out as in `to_png` with each
+    /// cell a square of `cell_px` units, and with the same `palette`.
+    /// Horizontal runs of cells in the same state are merged, so dense
+    /// universes stay small.
+    pub fn to_svg(&self, cell_px: u32, grid: bool, palette: &[u32]) -> String {
+        let palette = Palette::new(&self.rule, palette);
+        svg::write(self.width, self.height, &self.current_cells(), cell_px, grid, &palette)
+    }
+
+    /// Run the universe for `generations` generations, recording an
+    /// animated GIF of the cells in view with a frame every `skip`
+    /// generations, shown for `delay_ms` milliseconds each. Each cell is a
+    /// square of `cell_px` pixels. With `crop`, the frames are cut down to
+    /// the smallest rectangle holding every cell that is not dead in any of
+    /// them.
+    pub fn record_gif(
+        &mut self,
+        generations: u32,
+        cell_px: u32,
+        delay_ms: u32,
+        skip: u32,
+        crop: bool,
+    ) -> Result<Vec<u8>, String> {
+        if cell_px == 0 {
+            return Err(String::from("cells must be at least one pixel wide"));
+        }
+        if skip == 0 {
+            return Err(String::from("frames must be at least one generation apart"));
+        }
+        let mut animation = Animation::new(self.width, self.height);
+        animation.push(&self.current_cells());
+        let mut generation = 0;
+        while generation < generations {
+            let step = skip.min(generations - generation);
+            self.step_by(step as u64);
+            generation += step;
+            animation.push(&self.current_cells());
+        }
+        if crop {
+            animation.crop();
+        }
+        animation.to_gif(cell_px, delay_ms, &Palette::new(&self.rule, &[]))
+    }
+
+    /// Replace the cells in view with a PNG image, one cell per pixel from
+    /// the top left. Pixels darker than a luminance of `threshold` (0 to
+    /// 255) are dead cells; brighter pixels are live cells, or with more
+    /// than two states, dying cells the darker they are. Pixels outside a
+    /// bounded universe are left out.
+    pub fn load_png(&mut self, bytes: &[u8], threshold: u8) -> Result<(), String> {
+        let image = snapshot::decode(bytes)?;
+        self.clear_all();
+        let cells = image.cells(&self.rule, threshold);
+        for row in 0..image.height {
+            for col in 0..image.width {
+                let cell = cells[(row * image.width + col) as usize];
+                let outside = row >= self.height || col >= self.width;
+                if cell != Cell::DEAD && (self.plane.is_some() || !outside) {
+                    self.set_cell_at(row as i64, col as i64, cell);
+                }
+            }
+        }
+        self.history.commit();
+        Ok(())
+    }
+
+    /// Set the rule used by `tick` from a B/S (`B36/S23`), isotropic
+    /// non-totalistic (`B2-a/S12`), Generations (`B2/S/C3`, `345/2/4`),
+    /// Larger than Life (`R5,C0,M1,S34..58,B34..45,NM`) or legacy S/B
+    /// (`23/36`) rulestring.
+    ///
+    /// A Golly style topology may follow the rule after a colon, as in
+    /// `B3/S23:K64*,64`; see `set_topology`.
+    pub fn set_rule(&mut self, rule: &str) -> Result<(), String> {
+        let (rule, topology) = match rule.find(':') {
+            Some(colon) => (&rule[..colon], Some(&rule[colon + 1..])),
+            None => (rule, None),
+        };
+        let rule: Rule = rule.parse()?;
+        let topology = topology.map(Topology::parse).transpose()?;
+        self.check_topology(&rule, topology.map_or(self.topology, |(topology, _, _)| topology))?;
+        self.rule = rule;
+        if let Some((topology, width, height)) = topology {
+            self.apply_topology(topology, width, height);
+        }
+        self.dirty = DirtyTiles::new(self.width, self.height);
+        self.kill_extra_states();
+        Ok(())
+    }
+
+    /// Set how the edges of the universe are joined from a Golly style
+    /// specification: `P64,64` for a bounded plane, `T64,64` for a torus,
+    /// `K64*,64` for a Klein bottle twisted along the marked size, `C64,64`
+    /// for a cross-surface or `S64` for a sphere. The universe is resized and
+    /// cleared when the size differs from the current one.
+    ///
+    /// `P0,0` makes the universe an unbounded plane that grows as patterns
+    /// expand. The current cells are kept, and the universe's width and
+    /// height become the size of the viewport onto the plane.
+    pub fn set_topology(&mut self, spec: &str) -> Result<(), String> {
+        let (topology, width, height) = Topology::parse(spec)?;
+        self.check_topology(&self.rule, topology)?;
+        self.apply_topology(topology, width, height);
+        Ok(())
+    }
+
+    /// Move the viewport of an unbounded plane so that its top left cell is
+    /// at `row` and `col` of the plane.
+    pub fn set_viewport(&mut self, row: i32, col: i32) {
+        self.viewport = (row as i64, col as i64);
+        self.sync_viewport();
+    }
+
+    pub fn viewport_row(&self) -> i32 {
+        self.viewport.0 as i32
+    }
+
+    pub fn viewport_col(&self) -> i32 {
+        self.viewport.1 as i32
+    }
+
+    /// The states of the `height` by `width` region of cells whose top left
+    /// cell is at `row` and `col`, row by row. On an unbounded plane these
+    /// are plane coordinates, otherwise positions beyond the edges are mapped
+    /// by the universe's topology.
+    pub fn region(&self, row: i32, col: i32, height: u32, width: u32) -> Vec<u8> {
+        let (row, col) = (row as i64, col as i64);
+        match &self.plane {
+            Some(plane) => plane
+                .region(row, col, height, width)
+                .into_iter()
+                .map(Cell::state)
+                .collect(),
+            None => (row..row + height as i64)
+                .flat_map(|row| (col..col + width as i64).map(move |col| (row, col)))
+                .map(|(row, col)| self.cell_at(row, col).state())
+                .collect(),
+        }
+    }
+
+    /// The Golly style specification of the universe's topology and size.
+    pub fn topology(&self) -> String {
+        self.topology.spec(self.width, self.height)
+    }
+
+    /// The current rule in canonical B/S notation.
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// The number of cell states of the current rule: 2 for Life-like rules,
+    /// more for Generations rules.
+    pub fn states(&self) -> u8 {
+        self.rule.states()
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -431,42 +1300,228 @@ impl Universe {
         self.size
     }
 
-    pub fn cells(&self) -> *const Cell {
+    pub fn cells(&mut self) -> *const Cell {
+        self.unpack();
         self.cells[self.cells_idx].as_ptr()
     }
 
+    /// The number of live cells, in the whole plane when it is unbounded.
+    pub fn population(&self) -> u32 {
+        let population = match (&self.plane, self.statistics.population()) {
+            (Some(plane), _) => plane.population(),
+            (None, Some(population)) => population,
+            (None, None) => statistics::count(&self.current_cells()),
+        };
+        population.min(u32::MAX as u64) as u32
+    }
+
+    /// The top row, left column, height and width of the smallest box
+    /// holding every cell that is not dead, relative to the viewport of an
+    /// unbounded plane, or nothing when there are none.
+    pub fn bounding_box(&self) -> Vec<i32> {
+        let cells = self.occupied_cells();
+        if cells.is_empty() {
+            return Vec::new();
+        }
+        let (top, left, bottom, right) = cells.iter().fold(
+            (i64::MAX, i64::MAX, i64::MIN, i64::MIN),
+            |(top, left, bottom, right), &(row, col, _)| (top.min(row), left.min(col), bottom.max(row), right.max(col)),
+        );
+        let (row, col) = match self.plane {
+            Some(_) => (top - self.viewport.0, left - self.viewport.1),
+            None => (top, left),
+        };
+        vec![row as i32, col as i32, (bottom - top + 1) as i32, (right - left + 1) as i32]
+    }
+
+    /// The number of cells that came alive in the last `tick` or
+    /// `step_by`, in the viewport of an unbounded plane.
+    pub fn births(&self) -> u32 {
+        self.statistics.births()
+    }
+
+    /// The number of live cells that died, or started dying, in the last
+    /// `tick` or `step_by`, in the viewport of an unbounded plane.
+    pub fn deaths(&self) -> u32 {
+        self.statistics.deaths()
+    }
+
+    /// Keep the population after each of the last `length` calls to `tick`
+    /// or `step_by`, or none for 0, the default.
+    pub fn set_history_length(&mut self, length: u32) {
+        self.statistics.set_history_length(length as usize);
+    }
+
+    /// The population after each of the most recent steps, oldest first.
+    pub fn population_history(&self) -> Vec<u32> {
+        self.statistics.history()
+    }
+
+    pub fn clear_population_history(&mut self) {
+        self.statistics.clear_history();
+    }
+
     pub fn set_width(&mut self, width: u32) {
         self.width = width;
-        self.cells[0] = (0..width * self.height).map(|_i| Cell::Dead).collect();
-        self.cells[1] = (0..width * self.height).map(|_i| Cell::Dead).collect();
+        self.cells[0] = (0..width * self.height).map(|_i| Cell::DEAD).collect();
+        self.cells[1] = (0..width * self.height).map(|_i| Cell::DEAD).collect();
+        self.packed = None;
+        self.packed_ahead = false;
+        self.dirty = DirtyTiles::new(self.width, self.height);
+        self.statistics.invalidate();
+        self.history.clear();
+        self.sync_viewport();
     }
 
     pub fn set_height(&mut self, height: u32) {
         self.height = height;
-        self.cells[0] = (0..self.width * height).map(|_i| Cell::Dead).collect();
-        self.cells[1] = (0..self.width * height).map(|_i| Cell::Dead).collect();
+        self.cells[0] = (0..self.width * height).map(|_i| Cell::DEAD).collect();
+        self.cells[1] = (0..self.width * height).map(|_i| Cell::DEAD).collect();
+        self.packed = None;
+        self.packed_ahead = false;
+        self.dirty = DirtyTiles::new(self.width, self.height);
+        self.statistics.invalidate();
+        self.history.clear();
+        self.sync_viewport();
     }
 
     pub fn set_size(&mut self, size: u32) {
         self.size = size as u8;
     }
 
+    /// Resize the universe, clearing its cells unless it is unbounded.
     pub fn resize(&mut self, width: u32, height: u32, size: u32) {
-        self.width = width;
-        self.height = height;
+        let cleared = match self.plane {
+            Some(_) => Vec::new(),
+            None => self
+                .occupied_cells()
+                .into_iter()
+                .map(|(row, col, old)| Change {
+                    row,
+                    col,
+                    old,
+                    new: Cell::DEAD,
+                })
+                .collect(),
+        };
+        self.history.push(Entry::Resize {
+            old: (self.width, self.height, self.size),
+            new: (width, height, size as u8),
+            cleared,
+        });
         self.size = size as u8;
-        self.cells[0] = (0..width * height).map(|_i| Cell::Dead).collect();
-        self.cells[1] = (0..width * height).map(|_i| Cell::Dead).collect();
-        render::resize_canvas((size as u32 + 1) * width + 1, (size as u32 + 1) * height + 1)
-            .unwrap();
+        self.resize_cells(width, height);
     }
 
     pub fn toggle_cell(&mut self, row: u32, col: u32) {
-        let idx = self.get_index(row, col);
-        self.cells[self.cells_idx][idx].toggle();
+        let mut cell = self.cell_at(row as i64, col as i64);
+        cell.toggle();
+        self.set_cell_at(row as i64, col as i64, cell);
+        self.history.commit();
+    }
+
+    /// Undo the last edit, resize or generation, returning whether there
+    /// was one. Cells edited by one operation, such as seeding a pattern,
+    /// are undone together, and the generations of one `tick` or `step_by`
+    /// are stepped back through together.
+    pub fn undo(&mut self) -> bool {
+        match self.history.take_undo() {
+            Some(entry) => {
+                self.apply(&entry, false);
+                self.history.undone(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redo the last step undone, returning whether there was one. A step
+    /// can no longer be redone once the universe is edited or advanced.
+    pub fn redo(&mut self) -> bool {
+        match self.history.take_redo() {
+            Some(entry) => {
+                self.apply(&entry, true);
+                self.history.redone(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
     }
 
-    pub fn seed_population(&mut self, row: u32, col: u32, pop_name: String, h_flip: bool, v_flip: bool, invert: bool) {
+    /// Keep the last `steps` edits, resizes and generations to undo, 100 by
+    /// default, or none for 0.
+    pub fn set_undo_limit(&mut self, steps: u32) {
+        self.history.set_limit(steps as usize);
+    }
+
+    /// Whether `tick`, `step_by` and `run_until_stable` can be undone. They
+    /// cannot by default, as recording one compares every cell before and
+    /// after it; edits and resizes made before them can then still be
+    /// undone on the cells they left. A HashLife plane never records them.
+    pub fn set_undo_generations(&mut self, enabled: bool) {
+        self.history.set_generations(enabled);
+    }
+
+    /// The name of every pattern `seed_population` can seed, in the order
+    /// they are listed in.
+    pub fn list_patterns(&self) -> Vec<String> {
+        self.library.patterns().iter().map(Pattern::name).collect()
+    }
+
+    /// The cells, category, period and author of the pattern called `name`.
+    pub fn pattern_info(&self, name: &str) -> Result<Pattern, String> {
+        self.library.get(name).cloned()
+    }
+
+    /// Add a run length encoded pattern that `seed_population` can seed as
+    /// `name`, replacing the registered pattern of that name. Patterns may
+    /// only have live cells, and built in patterns cannot be replaced.
+    pub fn register_pattern(&mut self, name: &str, rle: &str) -> Result<(), String> {
+        let pattern = Pattern::from_rle(name, rle)?;
+        self.library.register(pattern)
+    }
+
+    pub fn unregister_pattern(&mut self, name: &str) -> Result<(), String> {
+        self.library.unregister(name)
+    }
+
+    /// The name and run length encoding of every registered pattern, one
+    /// after the other, to be registered again with `register_pattern`.
+    pub fn export_patterns(&self) -> Vec<String> {
+        self.library
+            .registered()
+            .iter()
+            .flat_map(|pattern| vec![pattern.name(), pattern.to_rle()])
+            .collect()
+    }
+
+    /// Seed the pattern called `pop_name` centered on `row` and `col`. With
+    /// `invert` rows and columns are swapped, then `v_flip` mirrors the
+    /// pattern top to bottom and `h_flip` left to right.
+    ///
+    /// `mode` combines the pattern with the cells under it: `copy` replaces
+    /// them, `or` adds the pattern's cells, `xor` toggles the cells under
+    /// them, `and` keeps only the cells under them and `and_not` clears the
+    /// cells under them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn seed_population(
+        &mut self,
+        row: u32,
+        col: u32,
+        pop_name: String,
+        h_flip: bool,
+        v_flip: bool,
+        invert: bool,
+        mode: &str,
+    ) -> Result<(), String> {
         // log!(
         //     "Universe::seed_population() row: {}, col: {}, name: {}, h_flip: {}, v_flip: {}, invert: {}",
         //     row,
@@ -476,56 +1531,296 @@ impl Universe {
         //     v_flip,
         //     invert
         // );
-        let pop = Population::new(pop_name);
-        let (height, width) = if invert {
-            (pop.width, pop.height)
-        } else {
-            (pop.height, pop.width)
-        };
-        let row = (self.height + row) - (height / 2);
-        let col = (self.width + col) - (width / 2);
-        // log!("Universe::seed_population() adjusted row: {}, col: {}", row, col);
-        let mut cells = Vec::new();
-        for (cell_y, cell_x) in pop.cells {
-            let (cell_row, cell_col) = if invert {
-                (cell_x, cell_y)
-            } else {
-                (cell_y, cell_x)
-            };
-            let row = if v_flip {
-                row + height - cell_row
-            } else {
-                row + cell_row
-            };
-            let col = if h_flip {
-                col + width - cell_col
-            } else {
-                col + cell_col
-            };
-            cells.push((row, col));
+        let mut transform = Transform::Identity;
+        if invert {
+            transform = transform.then(Transform::Transpose);
+        }
+        if v_flip {
+            transform = transform.then(Transform::FlipVertical);
         }
-        self.clear_cells(row, col, width, height);
-        self.set_cells(cells.as_slice());
+        if h_flip {
+            transform = transform.then(Transform::FlipHorizontal);
+        }
+        self.seed(row, col, &pop_name, transform, mode)
+    }
+
+    /// Seed the pattern called `pop_name` centered on `row` and `col`,
+    /// rotated or reflected by `transform`: `identity`, `rot90`, `rot180`
+    /// or `rot270` for clockwise rotations, `flip_h`, `flip_v`, `transpose`
+    /// or `anti_transpose`, or several of them separated by spaces, applied
+    /// from left to right. `mode` is as for `seed_population`.
+    pub fn seed_transformed(
+        &mut self,
+        row: u32,
+        col: u32,
+        pop_name: &str,
+        transform: &str,
+        mode: &str,
+    ) -> Result<(), String> {
+        let transform: Transform = transform.parse()?;
+        self.seed(row, col, pop_name, transform, mode)
     }
 
     pub fn destroy_all_life(&mut self) {
-        self.clear_cells(0, 0, self.width, self.height);
+        self.clear_all();
+        self.history.commit();
+    }
+
+    /// Load a run length encoded pattern with its top left corner at `row`
+    /// and `col`. The pattern's rule, when it has one, becomes the
+    /// universe's rule. Only the pattern's live and dying cells are set.
+    pub fn load_rle(&mut self, text: &str, row: i32, col: i32) -> Result<(), String> {
+        let rle = Rle::parse(text)?;
+        if let Some(rule) = &rle.rule {
+            self.set_rule(rule)?;
+        }
+        if let Some(&(_, _, cell)) = rle.cells.iter().find(|(_, _, cell)| cell.state() >= self.rule.states()) {
+            return Err(format!(
+                "pattern has cells in state {} but rule \"{}\" only has {} states",
+                cell.state(),
+                self.rule,
+                self.rule.states()
+            ));
+        }
+        for (cell_row, cell_col, cell) in rle.cells {
+            self.set_cell_at(row as i64 + cell_row as i64, col as i64 + cell_col as i64, cell);
+        }
+        self.history.commit();
+        Ok(())
+    }
+
+    /// Paste a run length encoded pattern, such as clipboard contents, with
+    /// its top left corner at `row` and `col`, combined with the cells under
+    /// it by `mode` as in `seed_population`. Unlike `load_rle`, the rule is
+    /// kept.
+    pub fn paste_rle(&mut self, text: &str, row: i32, col: i32, mode: &str) -> Result<(), String> {
+        let mode: PasteMode = mode.parse()?;
+        let rle = Rle::parse(text)?;
+        if let Some(&(_, _, cell)) = rle.cells.iter().find(|(_, _, cell)| cell.state() >= self.rule.states()) {
+            return Err(format!(
+                "pattern has cells in state {} but rule \"{}\" only has {} states",
+                cell.state(),
+                self.rule,
+                self.rule.states()
+            ));
+        }
+        self.paste(row as i64, col as i64, rle.height, rle.width, &rle.cells, mode)?;
+        self.history.commit();
+        Ok(())
+    }
+
+    /// The universe's cells, cropped to those that are not dead, run length
+    /// encoded along with the rule and, unless it is unbounded, topology.
+    pub fn to_rle(&self) -> String {
+        let cells = self.occupied_cells();
+        let top = cells.iter().map(|&(row, _, _)| row).min().unwrap_or(0);
+        let left = cells.iter().map(|&(_, col, _)| col).min().unwrap_or(0);
+        let bottom = cells.iter().map(|&(row, _, _)| row + 1).max().unwrap_or(0);
+        let right = cells.iter().map(|&(_, col, _)| col + 1).max().unwrap_or(0);
+        Rle {
+            rule: Some(self.rule_spec()),
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+            multi_state: self.rule.states() > 2,
+            cells: cells
+                .into_iter()
+                .map(|(row, col, cell)| ((row - top) as u32, (col - left) as u32, cell))
+                .collect(),
+            ..Rle::default()
+        }
+        .to_string()
+    }
+
+    /// Load a plaintext `.cells` pattern with its top left corner at `row`
+    /// and `col`. Only the pattern's live cells are set.
+    pub fn load_cells(&mut self, text: &str, row: i32, col: i32) -> Result<(), String> {
+        let pattern = Plaintext::parse(text)?;
+        for (cell_row, cell_col) in pattern.cells {
+            self.set_cell_at(row as i64 + cell_row as i64, col as i64 + cell_col as i64, Cell::ALIVE);
+        }
+        self.history.commit();
+        Ok(())
+    }
+
+    /// The universe's live cells, cropped to their bounding box, in the
+    /// plaintext `.cells` format.
+    pub fn to_cells(&self) -> String {
+        let cells = self.live_cells();
+        let top = cells.iter().map(|&(row, _)| row).min().unwrap_or(0);
+        let left = cells.iter().map(|&(_, col)| col).min().unwrap_or(0);
+        let bottom = cells.iter().map(|&(row, _)| row + 1).max().unwrap_or(0);
+        let right = cells.iter().map(|&(_, col)| col + 1).max().unwrap_or(0);
+        Plaintext {
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+            cells: cells
+                .into_iter()
+                .map(|(row, col)| ((row - top) as u32, (col - left) as u32))
+                .collect(),
+            ..Plaintext::default()
+        }
+        .to_string()
+    }
+
+    /// Load a Life 1.06 pattern, placing the cell at coordinates `0 0` at
+    /// `row` and `col`. Cells beyond the edges are placed according to the
+    /// universe's topology, as with `set_cells`.
+    pub fn load_life106(&mut self, text: &str, row: i32, col: i32) -> Result<(), String> {
+        for (cell_row, cell_col) in life106::parse(text)? {
+            self.set_cell_at(row as i64 + cell_row, col as i64 + cell_col, Cell::ALIVE);
+        }
+        self.history.commit();
+        Ok(())
+    }
+
+    /// The universe's live cells in the Life 1.06 format, with coordinates
+    /// `0 0` at the top left cell of the universe, or at the origin of an
+    /// unbounded plane.
+    pub fn to_life106(&self) -> String {
+        life106::write(&self.live_cells())
+    }
+
+    /// Replace the universe's cells with a macrocell pattern, centered on
+    /// the origin of an unbounded plane or on the center of a bounded
+    /// universe. The pattern's rule, when it has one, becomes the universe's
+    /// rule.
+    ///
+    /// With the HashLife algorithm the pattern's quadtree is loaded as it
+    /// is, so patterns far too large for a cell buffer can be loaded.
+    /// Otherwise every cell is set one by one, so patterns with more than
+    /// 2^24 cells are refused.
+    pub fn load_macrocell(&mut self, text: &str) -> Result<(), String> {
+        let macrocell = Macrocell::parse(text)?;
+        let hashlife = matches!(self.plane, Some(Plane::HashLife(_)));
+        if !hashlife && macrocell.population() > macrocell::MAX_CELLS {
+            return Err(format!(
+                "pattern has {} cells, too many to load without the hashlife algorithm",
+                macrocell.population()
+            ));
+        }
+        if let Some(rule) = &macrocell.rule {
+            self.set_rule(rule)?;
+        }
+        if macrocell.max_state() >= self.rule.states() {
+            return Err(format!(
+                "pattern has cells in state {} but rule \"{}\" only has {} states",
+                macrocell.max_state(),
+                self.rule,
+                self.rule.states()
+            ));
+        }
+        self.clear_all();
+        match &mut self.plane {
+            Some(Plane::HashLife(hashlife)) => {
+                // The quadtree is loaded without going through the cells,
+                // so there is nothing to undo it by.
+                hashlife.load_macrocell(&macrocell);
+                self.history.clear();
+            }
+            Some(plane) => {
+                for (row, col, cell) in macrocell.cells() {
+                    self.history.record(row, col, plane.get(row, col), cell);
+                    plane.set(row, col, cell);
+                }
+            }
+            None => {
+                let (top, left) = ((self.height / 2) as i64, (self.width / 2) as i64);
+                for (row, col, cell) in macrocell.cells() {
+                    self.set_cell_at(top + row, left + col, cell);
+                }
+            }
+        }
+        self.history.commit();
+        self.sync_viewport();
+        Ok(())
+    }
+
+    /// The universe's cells as a macrocell pattern, centered as by
+    /// `load_macrocell`, with the rule and, unless it is unbounded,
+    /// topology.
+    pub fn to_macrocell(&self) -> String {
+        let mut macrocell = match &self.plane {
+            Some(Plane::HashLife(hashlife)) => hashlife.to_macrocell(),
+            Some(_) => Macrocell::from_cells(&self.occupied_cells(), self.rule.states() > 2),
+            None => {
+                let (top, left) = ((self.height / 2) as i64, (self.width / 2) as i64);
+                let cells: Vec<(i64, i64, Cell)> = self
+                    .occupied_cells()
+                    .into_iter()
+                    .map(|(row, col, cell)| (row - top, col - left, cell))
+                    .collect();
+                Macrocell::from_cells(&cells, self.rule.states() > 2)
+            }
+        };
+        macrocell.rule = Some(self.rule_spec());
+        macrocell.to_string()
+    }
+
+    /// The apgcode of the object holding the live cell at `row` and `col`:
+    /// the cells connected to it, across the edges of a torus, run on their
+    /// own until they repeat.
+    pub fn apgcode(&self, row: i32, col: i32) -> Result<String, String> {
+        if self.rule.states() != 2 {
+            return Err(format!("rule \"{}\" has no apgcodes, it is not a two-state rule", self.rule));
+        }
+        let start = match self.plane {
+            Some(_) => (self.viewport.0 + row as i64, self.viewport.1 + col as i64),
+            None => (row as i64, col as i64),
+        };
+        let live: HashSet<(i64, i64)> = self.live_cells().into_iter().collect();
+        let start = match self.wrap(start.0, start.1) {
+            Some(start) if live.contains(&start) => start,
+            _ => return Err(format!("there is no live cell at row {}, column {}", row, col)),
+        };
+        let object = object::cluster(&live, start, 1, |row, col| self.wrap(row, col));
+        if object.wraps_around {
+            return Err(format!("the object at row {}, column {} wraps around the universe", row, col));
+        }
+        let cells: Vec<(i64, i64, Cell)> =
+            object.positions.values().map(|&(row, col)| (row, col, Cell::ALIVE)).collect();
+        match object::evolve(&self.rule, &cells, object::MAX_PERIOD) {
+            Some(evolution) => Ok(apgcode::encode(&evolution)),
+            None => Err(format!(
+                "the object at row {}, column {} dies or does not repeat within {} generations",
+                row,
+                col,
+                object::MAX_PERIOD
+            )),
+        }
+    }
+
+    /// Count the objects in the universe by kind. Objects crossing the
+    /// edges of a torus are followed across them.
+    pub fn census(&self) -> Census {
+        Census::take(&self.rule, &self.occupied_cells(), |row, col| self.wrap(row, col))
+    }
+
+    /// Add the object of an apgcode with its top left corner at `row` and
+    /// `col`.
+    pub fn load_apgcode(&mut self, code: &str, row: u32, col: u32) -> Result<(), String> {
+        let cells: Vec<(u32, u32)> = apgcode::decode(code)?
+            .into_iter()
+            .map(|(cell_row, cell_col)| (row + cell_row as u32, col + cell_col as u32))
+            .collect();
+        self.set_cells(&cells);
+        Ok(())
     }
 
     pub fn random_population(&mut self, density: f32) {
         self.clear_cells(0, 0, self.width, self.height);
         for row in 0..self.height {
             for col in 0..self.width {
-                let idx = self.get_index(row, col);
-                self.cells[self.cells_idx][idx] = {
+                let cell = {
                     if Math::random() < density as f64 {
-                        Cell::Alive
+                        Cell::ALIVE
                     } else {
-                        Cell::Dead
+                        Cell::DEAD
                     }
                 };
+                self.set_cell_at(row as i64, col as i64, cell);
             }
         }
+        self.history.commit();
     }
 }
 
@@ -533,12 +1828,16 @@ use std::fmt;
 
 impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for line in (self.cells[self.cells_idx]).as_slice().chunks(self.width as usize) {
+        for line in self.current_cells().chunks(self.width as usize) {
             for &cell in line {
-                let symbol = if cell == Cell::Dead { '◻' } else { '◼' };
+                let symbol = match cell {
+                    Cell::DEAD => '◻',
+                    Cell::ALIVE => '◼',
+                    _ => '◧',
+                };
                 write!(f, "{}", symbol)?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
 
         Ok(())
@@ -549,6 +1848,14 @@ impl fmt::Display for Universe {
 mod tests {
     use super::*;
 
+    /// A `width` by `height` universe with no live cells.
+    fn empty_universe(width: u32, height: u32) -> Universe {
+        let mut universe = Universe::new();
+        universe.set_width(width);
+        universe.set_height(height);
+        universe
+    }
+
     #[test]
     fn test_spaceship() {
         let mut input_universe = Universe::new();
@@ -570,4 +1877,791 @@ mod tests {
         println!("{}", expected_universe.render_to_string());
         assert_eq!(&input_universe.get_cells(), &expected_universe.get_cells());
     }
+
+    #[test]
+    fn test_set_rule() {
+        let mut universe = empty_universe(6, 6);
+        assert!(universe.set_rule("B2/S").is_ok());
+        assert_eq!(universe.rule(), "B2/S");
+        assert!(universe.set_rule("B9/S23").is_err());
+        assert_eq!(universe.rule(), "B2/S");
+
+        // Under Seeds a domino gives birth to two dominoes and dies.
+        universe.set_cells(&[(2, 2), (2, 3)]);
+        universe.tick();
+
+        let mut expected_universe = empty_universe(6, 6);
+        expected_universe.set_cells(&[(1, 2), (1, 3), (3, 2), (3, 3)]);
+        assert_eq!(&universe.get_cells(), &expected_universe.get_cells());
+    }
+
+    #[test]
+    fn test_non_totalistic_rule() {
+        let mut universe = empty_universe(6, 6);
+        universe.set_rule("B2-a/S12").unwrap();
+
+        // A domino is a still life: its only 2-neighbor births are 2a.
+        universe.set_cells(&[(2, 2), (2, 3)]);
+        let domino = universe.get_cells().to_vec();
+        universe.tick();
+        assert_eq!(universe.get_cells(), domino.as_slice());
+
+        // A diagonal pair fills in to a block through 2e births.
+        universe.destroy_all_life();
+        universe.set_cells(&[(2, 2), (3, 3)]);
+        universe.tick();
+
+        let mut expected_universe = empty_universe(6, 6);
+        expected_universe.set_cells(&[(2, 2), (2, 3), (3, 2), (3, 3)]);
+        assert_eq!(&universe.get_cells(), &expected_universe.get_cells());
+    }
+
+    #[test]
+    fn test_generations_rule() {
+        let mut universe = empty_universe(6, 6);
+        universe.set_rule("/2/3").unwrap();
+        assert_eq!(universe.states(), 3);
+
+        universe.set_cells(&[(2, 2), (2, 3)]);
+        universe.tick();
+
+        let mut expected = vec![Cell::DEAD; 36];
+        for &idx in &[8, 9, 20, 21] {
+            expected[idx] = Cell::ALIVE;
+        }
+        for &idx in &[14, 15] {
+            expected[idx] = Cell::new(2);
+        }
+        assert_eq!(universe.get_cells(), expected.as_slice());
+
+        universe.tick();
+        assert!(universe.get_cells()[14..16].iter().all(|&cell| cell == Cell::DEAD));
+    }
+
+    #[test]
+    fn test_larger_than_life_rule() {
+        let mut universe = empty_universe(8, 8);
+
+        // Range 1 Larger than Life with these intervals is Conway's Life.
+        universe.set_rule("R1,C0,M0,S2..3,B3..3,NM").unwrap();
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        universe.tick();
+
+        let mut expected_universe = empty_universe(8, 8);
+        expected_universe.set_cells(&[(2, 1), (2, 3), (3, 2), (3, 3), (4, 2)]);
+        assert_eq!(&universe.get_cells(), &expected_universe.get_cells());
+    }
+
+    #[test]
+    fn test_topology() {
+        let mut universe = Universe::new();
+        universe.set_rule("B3/S23:P6,6").unwrap();
+        assert_eq!(universe.topology(), "P6,6");
+        assert_eq!(universe.width(), 6);
+
+        // A blinker against the edge of a bounded plane loses its outer cell
+        // to the void and becomes a domino that dies out.
+        universe.set_cells(&[(0, 1), (0, 2), (0, 3)]);
+        universe.tick();
+        let mut expected_universe = Universe::new();
+        expected_universe.set_topology("P6,6").unwrap();
+        expected_universe.set_cells(&[(0, 2), (1, 2)]);
+        assert_eq!(&universe.get_cells(), &expected_universe.get_cells());
+
+        // On a Klein bottle twisted top to bottom, a blinker standing across
+        // the top edge comes back mirrored.
+        universe.set_topology("K6*,6").unwrap();
+        universe.destroy_all_life();
+        universe.set_cells(&[(0, 0), (0, 1), (0, 2)]);
+        universe.tick();
+        let mut expected_universe = Universe::new();
+        expected_universe.set_topology("K6*,6").unwrap();
+        expected_universe.set_cells(&[(0, 1), (1, 1), (5, 4)]);
+        assert_eq!(&universe.get_cells(), &expected_universe.get_cells());
+
+        assert!(universe.set_topology("K6,6").is_err());
+        assert!(universe.set_rule("B3/S23:X6,6").is_err());
+        assert_eq!(universe.rule(), "B3/S23");
+    }
+
+    #[test]
+    fn test_unbounded_plane() {
+        let mut universe = empty_universe(8, 8);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        universe.set_topology("P0,0").unwrap();
+        assert_eq!(universe.topology(), "P0,0");
+
+        // The glider leaves the 8x8 viewport without wrapping around.
+        for _ in 0..40 {
+            universe.tick();
+        }
+        assert!(universe.get_cells().iter().all(|&cell| cell == Cell::DEAD));
+        let glider = universe.region(11, 11, 3, 3);
+        assert_eq!(glider, vec![0, 1, 0, 0, 0, 1, 1, 1, 1]);
+
+        universe.set_viewport(10, 10);
+        assert_eq!(universe.get_cells().iter().filter(|&&cell| cell == Cell::ALIVE).count(), 5);
+
+        assert!(universe.set_rule("B0/S8").is_err());
+        assert!(universe.set_rule("B3/S23:T8,8").is_ok());
+        assert_eq!(universe.topology(), "T8,8");
+
+        // A rule and topology are checked together before either is set.
+        universe.set_rule("B0/S8").unwrap();
+        assert!(universe.set_rule("B0/S23:P0,0").is_err());
+        assert_eq!((universe.rule(), universe.topology()), ("B0/S8".to_string(), "T8,8".to_string()));
+        universe.set_rule("B3/S23:P0,0").unwrap();
+        assert_eq!((universe.rule(), universe.topology()), ("B3/S23".to_string(), "P0,0".to_string()));
+    }
+
+    #[test]
+    fn test_bitpacked() {
+        let glider = [(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)];
+        let mut generic = Universe::new();
+        generic.set_topology("T70,9").unwrap();
+        generic.set_cells(&glider);
+        let mut packed = Universe::new();
+        packed.set_topology("T70,9").unwrap();
+        packed.set_algorithm("bitpacked").unwrap();
+        packed.set_cells(&glider);
+
+        // The glider wraps around the short edge and across the word
+        // boundary of the 70 column rows.
+        for _ in 0..50 {
+            generic.tick();
+            packed.tick();
+            assert_eq!(packed.get_cells(), generic.get_cells());
+        }
+        generic.step_by(300);
+        packed.step_by(300);
+        assert_eq!(packed.get_cells(), generic.get_cells());
+
+        // Generations only advance the packed cells, which are unpacked when
+        // the cells are read, with the tiles that changed since.
+        for _ in 0..4 {
+            generic.tick();
+            packed.tick();
+        }
+        assert!(packed.packed_ahead);
+        assert_eq!(packed.population(), generic.population());
+        assert_eq!(packed.occupied_cells(), generic.occupied_cells());
+        assert_eq!(packed.to_string(), generic.to_string());
+        assert_eq!(packed.dirty_tiles(), vec![0, 0]);
+        assert!(!packed.packed_ahead);
+        assert_eq!(packed.get_cells(), generic.get_cells());
+
+        packed.toggle_cell(0, 0);
+        generic.toggle_cell(0, 0);
+        packed.tick();
+        generic.tick();
+        assert_eq!(packed.get_cells(), generic.get_cells());
+
+        assert!(packed.set_rule("B2-a/S12").is_err());
+        assert!(packed.set_rule("B3/S23/C3").is_err());
+        assert!(packed.set_topology("K8*,8").is_err());
+        assert!(packed.set_rule("B36/S23:P8,8").is_ok());
+    }
+
+    #[test]
+    fn test_active_tiles() {
+        // A glider heading off the bottom right corner, a blinker on the left
+        // edge and a block.
+        let cells = [
+            (61, 92),
+            (62, 93),
+            (63, 91),
+            (63, 92),
+            (63, 93),
+            (40, 0),
+            (41, 0),
+            (42, 0),
+            (50, 50),
+            (50, 51),
+            (51, 50),
+            (51, 51),
+        ];
+        for topology in &["T100,70", "P100,70", "K100*,70", "C100,70", "S70"] {
+            // Larger than Life rules compute every cell, so this one, which is
+            // the same as Life, checks the tiles skipped by the other.
+            let mut active = Universe::new();
+            active.set_rule(&format!("B3/S23:{}", topology)).unwrap();
+            active.set_cells(&cells);
+            let mut every_cell = Universe::new();
+            every_cell.set_rule(&format!("R1,C0,M0,S2..3,B3..3,NM:{}", topology)).unwrap();
+            every_cell.set_cells(&cells);
+            for generation in 0..60 {
+                active.tick();
+                every_cell.tick();
+                assert_eq!(
+                    active.get_cells(),
+                    every_cell.get_cells(),
+                    "{} generation {}",
+                    topology,
+                    generation
+                );
+            }
+        }
+
+        // Once only still lifes are left no tile changes.
+        let mut universe = Universe::new();
+        universe.set_cells(&[(50, 50), (50, 51), (51, 50), (51, 51), (10, 10), (10, 11), (10, 12)]);
+        universe.tick();
+        assert_eq!(universe.tile_size(), 32);
+        assert_eq!(universe.dirty_tiles(), vec![0, 0]);
+        universe.toggle_cell(9, 11);
+        universe.toggle_cell(10, 11);
+        universe.toggle_cell(11, 11);
+        universe.tick();
+        assert!(universe.dirty_tiles().is_empty());
+        universe.toggle_cell(63, 63);
+        assert_eq!(universe.dirty_tiles(), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_rle() {
+        let mut universe = Universe::new();
+        universe
+            .load_rle("#N Glider\n#C A comment.\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!", 1, 1)
+            .unwrap();
+        let mut expected_universe = Universe::new();
+        expected_universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        assert_eq!(&universe.get_cells(), &expected_universe.get_cells());
+        assert_eq!(universe.to_rle(), "x = 3, y = 3, rule = B3/S23:T64,64\nbo$2bo$3o!\n");
+
+        let mut copy = Universe::new();
+        copy.set_topology("T8,8").unwrap();
+        copy.load_rle(&universe.to_rle(), 0, 0).unwrap();
+        assert_eq!(copy.topology(), "T64,64");
+        copy.load_rle("x = 3, y = 1\n3o!", 10, 62).unwrap();
+        assert_eq!(copy.get_cells()[10 * 64], Cell::ALIVE);
+
+        // Dying cells need a Generations rule.
+        assert!(copy.load_rle("x = 2, y = 1\nAB!", 0, 0).is_err());
+        copy.load_rle("x = 2, y = 1, rule = B2/S/C3\nAB!", 0, 0).unwrap();
+        assert_eq!(copy.get_cells()[1], Cell::new(2));
+        assert_eq!(
+            copy.to_rle(),
+            "x = 64, y = 11, rule = B2/S/C3:T64,64\nAB$2.A$3A8$A61.2A!\n"
+        );
+
+        let mut plane = Universe::new();
+        plane.set_topology("P0,0").unwrap();
+        plane.load_rle("2o$2o!", -100, -100).unwrap();
+        assert_eq!(plane.to_rle(), "x = 2, y = 2, rule = B3/S23\n2o$2o!\n");
+    }
+
+    #[test]
+    fn test_plaintext_and_life106() {
+        let mut universe = Universe::new();
+        universe.set_topology("T8,8").unwrap();
+        universe.load_cells("!Name: Glider\n.O.\n..O\nOOO\n", 1, 1).unwrap();
+        let mut expected_universe = Universe::new();
+        expected_universe.set_topology("T8,8").unwrap();
+        expected_universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        assert_eq!(&universe.get_cells(), &expected_universe.get_cells());
+        assert_eq!(universe.to_cells(), ".O.\n..O\nOOO\n");
+        assert_eq!(universe.to_life106(), "#Life 1.06\n2 1\n3 2\n1 3\n2 3\n3 3\n");
+
+        // Life 1.06 coordinates are relative to the origin and wrap around
+        // the torus.
+        universe.destroy_all_life();
+        universe
+            .load_life106("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n", 0, 0)
+            .unwrap();
+        expected_universe.destroy_all_life();
+        expected_universe.set_cells(&[(7, 0), (0, 1), (1, 7), (1, 0), (1, 1)]);
+        assert_eq!(&universe.get_cells(), &expected_universe.get_cells());
+
+        assert!(universe.load_cells("x", 0, 0).is_err());
+        assert!(universe.load_life106("1 1", 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_macrocell() {
+        let mut universe = Universe::new();
+        universe.set_topology("T16,16").unwrap();
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        let text = universe.to_macrocell();
+        assert_eq!(text, "[M2] (wasm-game-of-life)\n#R B3/S23:T16,16\n$..*$...*$.***$\n4 1 0 0 0\n");
+
+        let mut copy = Universe::new();
+        copy.load_macrocell(&text).unwrap();
+        assert_eq!(copy.topology(), "T16,16");
+        assert_eq!(&copy.get_cells(), &universe.get_cells());
+
+        // On an unbounded plane with HashLife the tree is loaded as is.
+        let mut plane = empty_universe(8, 8);
+        plane.set_topology("P0,0").unwrap();
+        plane.set_algorithm("hashlife").unwrap();
+        plane.load_macrocell("[M2]\n#R B3/S23\n$$$$$$$**$\n......**$\n4 0 1 2 0\n").unwrap();
+        plane.set_viewport(-2, -2);
+        assert_eq!(plane.region(-1, -2, 2, 4), vec![0, 0, 1, 1, 1, 1, 0, 0]);
+        assert_eq!(plane.get_cells().iter().filter(|&&cell| cell == Cell::ALIVE).count(), 4);
+        assert_eq!(plane.to_macrocell(), "[M2] (wasm-game-of-life)\n#R B3/S23\n$$$$$$$**$\n......**$\n4 0 1 2 0\n");
+
+        // A pattern 2^20 cells wide only loads as a tree.
+        let mut huge = String::from("[M2]\n**$**$\n");
+        for level in 4..=20 {
+            huge.push_str(&format!("{} {} {} {} {}\n", level, level - 3, level - 3, level - 3, level - 3));
+        }
+        plane.load_macrocell(&huge).unwrap();
+        let mut sparse = Universe::new();
+        sparse.set_topology("P0,0").unwrap();
+        assert!(sparse.load_macrocell(&huge).is_err());
+
+        // Multi-state patterns need a Generations rule.
+        assert!(copy.load_macrocell("[M2]\n1 2 0 0 0\n").is_err());
+        copy.load_macrocell("[M2]\n#R B2/S/C3\n1 2 0 0 0\n").unwrap();
+        let idx = copy.get_index(7, 7);
+        assert_eq!(copy.get_cells()[idx], Cell::new(2));
+        assert!(copy.to_macrocell().ends_with("#R B2/S/C3:T16,16\n1 2 0 0 0\n"));
+    }
+
+    #[test]
+    fn test_hashlife() {
+        let mut universe = empty_universe(8, 8);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        assert!(universe.set_algorithm("hashlife").is_err());
+        universe.set_topology("P0,0").unwrap();
+        universe.set_algorithm("HashLife").unwrap();
+        assert_eq!(universe.algorithm(), "hashlife");
+
+        // A glider moves one cell diagonally every four generations.
+        universe.step_by(4 * 1_000_000);
+        let glider = universe.region(1_000_001, 1_000_001, 3, 3);
+        assert_eq!(glider, vec![0, 1, 0, 0, 0, 1, 1, 1, 1]);
+
+        universe.tick();
+        universe.set_algorithm("generic").unwrap();
+        universe.step_by(3);
+        let glider = universe.region(1_000_002, 1_000_002, 3, 3);
+        assert_eq!(glider, vec![0, 1, 0, 0, 0, 1, 1, 1, 1]);
+
+        universe.set_algorithm("hashlife").unwrap();
+        universe.step_by(1 << 40);
+        assert_eq!(universe.population(), 5);
+        assert!(universe.set_rule("B3/S23/C3").is_err());
+        assert!(universe.set_rule("R2,C0,M1,S2..3,B3..3").is_err());
+        assert!(universe.set_topology("T8,8").is_err());
+        assert!(universe.set_rule("B36/S23").is_ok());
+        assert!(universe.set_algorithm("quicklife").is_err());
+    }
+
+    #[test]
+    fn test_apgcode() {
+        let mut universe = empty_universe(16, 16);
+        universe.load_apgcode("xs4_33", 1, 1).unwrap();
+        universe.load_apgcode("xp2_7", 1, 8).unwrap();
+        universe.load_apgcode("xq4_153", 8, 8).unwrap();
+        assert_eq!(universe.apgcode(2, 2).unwrap(), "xs4_33");
+        assert_eq!(universe.apgcode(3, 8).unwrap(), "xp2_7");
+        assert_eq!(universe.apgcode(10, 9).unwrap(), "xq4_153");
+        assert!(universe.apgcode(5, 5).is_err());
+        assert!(universe.load_apgcode("xs4_3?", 0, 0).is_err());
+
+        // A block across the corner of the torus is followed across its edges.
+        universe.destroy_all_life();
+        universe.set_cells(&[(0, 0), (0, 15), (15, 0), (15, 15)]);
+        assert_eq!(universe.apgcode(0, 0).unwrap(), "xs4_33");
+        assert_eq!(universe.apgcode(15, 0).unwrap(), "xs4_33");
+        universe.set_topology("P16,16").unwrap();
+        assert!(universe.apgcode(0, 0).is_err());
+
+        universe.set_rule("B3/S23/C3").unwrap();
+        assert!(universe.apgcode(2, 2).is_err());
+    }
+
+    #[test]
+    fn test_census() {
+        let mut universe = empty_universe(16, 16);
+        // A block across the corner of the torus, a blinker and a glider.
+        universe.set_cells(&[(0, 0), (0, 15), (15, 0), (15, 15), (5, 4), (5, 5), (5, 6)]);
+        universe.load_apgcode("xq4_153", 9, 9).unwrap();
+        let census = universe.census();
+        assert_eq!(census.objects(), 3);
+        assert_eq!((census.count("xs4_33"), census.count("xp2_7"), census.count("xq4_153")), (1, 1, 1));
+
+        // On a plane the block is four cells in the corners, which die.
+        universe.set_topology("P16,16").unwrap();
+        let census = universe.census();
+        assert_eq!(census.count("unknown"), 4);
+        assert_eq!(census.entry(0).unwrap().category(), "unknown");
+    }
+
+    #[test]
+    fn test_run_until_stable() {
+        let mut universe = empty_universe(16, 16);
+        // A pre-block becomes a block after one generation.
+        universe.set_cells(&[(2, 2), (2, 3), (3, 2)]);
+        let stabilization = universe.run_until_stable(10).unwrap();
+        assert_eq!((stabilization.generation(), stabilization.period()), (1, 1));
+        // The whole run is undone at once.
+        universe.set_undo_generations(true);
+        universe.destroy_all_life();
+        universe.set_cells(&[(2, 2), (2, 3), (3, 2)]);
+        universe.run_until_stable(10);
+        universe.undo();
+        assert_eq!(universe.live_cells(), vec![(2, 2), (2, 3), (3, 2)]);
+
+        // A glider on the torus comes back after 64 generations.
+        universe.destroy_all_life();
+        universe.load_apgcode("xq4_153", 4, 4).unwrap();
+        let stabilization = universe.run_until_stable(128).unwrap();
+        assert_eq!((stabilization.generation(), stabilization.period()), (0, 64));
+        assert!(universe.run_until_stable(100).is_none());
+
+        // A blinker on the unbounded plane.
+        universe.set_topology("P0,0").unwrap();
+        universe.destroy_all_life();
+        universe.set_cells(&[(3, 2), (3, 3), (3, 4)]);
+        let stabilization = universe.run_until_stable(10).unwrap();
+        assert_eq!((stabilization.generation(), stabilization.period()), (0, 2));
+    }
+
+    #[test]
+    fn test_detect_translation() {
+        let mut universe = empty_universe(32, 32);
+        let spaceships = [
+            ("glider", 4, (1, 1), "c/4 diagonal"),
+            ("lwss", 4, (0, 2), "c/2 orthogonal"),
+            ("mwss", 4, (0, 2), "c/2 orthogonal"),
+            ("hwss", 4, (0, 2), "c/2 orthogonal"),
+        ];
+        for &topology in ["T32,32", "P0,0"].iter() {
+            universe.set_topology(topology).unwrap();
+            for &(name, period, (dy, dx), speed) in spaceships.iter() {
+                universe.destroy_all_life();
+                // Across the corner of the torus.
+                universe.seed_transformed(31, 31, name, "identity", "copy").unwrap();
+                let cells = universe.occupied_cells();
+                let translation = universe.detect_translation(8).unwrap();
+                assert_eq!(
+                    (translation.period(), (translation.dy().abs(), translation.dx().abs())),
+                    (period, (dy, dx)),
+                    "{} on {}",
+                    name,
+                    topology
+                );
+                assert_eq!(translation.speed().as_deref(), Some(speed));
+                assert_eq!(universe.occupied_cells(), cells);
+            }
+        }
+
+        universe.set_topology("T32,32").unwrap();
+        universe.destroy_all_life();
+        assert!(universe.detect_translation(8).is_err());
+        universe.set_cells(&[(3, 2), (3, 3), (3, 4)]);
+        let translation = universe.detect_translation(8).unwrap();
+        assert_eq!((translation.period(), translation.dy(), translation.dx()), (2, 0, 0));
+        assert_eq!(translation.speed(), None);
+        universe.set_cells(&[(10, 10), (10, 11), (11, 10)]);
+        assert!(universe.detect_translation(8).is_err());
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut universe = empty_universe(16, 16);
+        assert_eq!(universe.population(), 0);
+        assert_eq!(universe.bounding_box(), Vec::<i32>::new());
+
+        universe.set_history_length(4);
+        universe.set_cells(&[(3, 2), (3, 3), (3, 4), (10, 10), (10, 11), (11, 10), (11, 11)]);
+        assert_eq!(universe.population(), 7);
+        assert_eq!(universe.bounding_box(), vec![3, 2, 9, 10]);
+        universe.tick();
+        assert_eq!((universe.births(), universe.deaths()), (2, 2));
+        assert_eq!(universe.bounding_box(), vec![2, 3, 10, 9]);
+        universe.toggle_cell(0, 0);
+        universe.step_by(5);
+        assert_eq!((universe.births(), universe.deaths()), (2, 2));
+        assert_eq!(universe.population_history(), vec![7, 7, 7, 7]);
+        assert_eq!(universe.population(), 7);
+
+        // The same on an unbounded plane, which counts every cell.
+        universe.set_topology("P0,0").unwrap();
+        universe.clear_population_history();
+        universe.set_viewport(4, 4);
+        universe.tick();
+        assert_eq!(universe.population(), 7);
+        assert_eq!(universe.bounding_box(), vec![-2, -1, 10, 9]);
+        assert_eq!(universe.population_history(), vec![7]);
+
+        // Every algorithm counts the same births and deaths.
+        for &(algorithm, topology) in [("bitpacked", "T16,16"), ("hashlife", "P0,0")].iter() {
+            universe.set_algorithm("generic").unwrap();
+            universe.set_topology(topology).unwrap();
+            universe.set_algorithm(algorithm).unwrap();
+            universe.destroy_all_life();
+            universe.set_viewport(0, 0);
+            universe.set_cells(&[(3, 2), (3, 3), (3, 4), (5, 5), (6, 6)]);
+            universe.tick();
+            assert_eq!((universe.births(), universe.deaths()), (3, 4), "{}", algorithm);
+            assert_eq!(universe.population(), 4, "{}", algorithm);
+        }
+
+        // And so do Larger than Life rules.
+        universe.set_algorithm("generic").unwrap();
+        universe.set_rule("R1,C0,M0,S2..3,B3..3,NM:T16,16").unwrap();
+        universe.destroy_all_life();
+        universe.set_cells(&[(3, 2), (3, 3), (3, 4), (5, 5), (6, 6)]);
+        universe.tick();
+        assert_eq!((universe.births(), universe.deaths()), (3, 4));
+        assert_eq!(universe.population(), 4);
+        assert_eq!(universe.dirty_tiles(), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut universe = empty_universe(16, 16);
+        assert!(!universe.can_undo());
+        assert!(!universe.undo());
+
+        // Generations are not recorded unless asked, but the edits before
+        // them can still be undone.
+        universe.seed_population(4, 4, String::from("block"), false, false, false, "copy").unwrap();
+        universe.tick();
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 0);
+        assert!(!universe.can_undo());
+        universe.set_undo_generations(true);
+
+        universe.toggle_cell(1, 1);
+        universe.seed_population(8, 8, String::from("glider"), false, false, false, "copy").unwrap();
+        let seeded = universe.occupied_cells();
+        universe.tick();
+        universe.step_by(3);
+        let stepped = universe.occupied_cells();
+        assert!(universe.can_undo());
+
+        // Step back through the generations, then the edits.
+        assert!(universe.undo());
+        assert!(universe.undo());
+        assert_eq!(universe.occupied_cells(), seeded);
+        assert!(universe.undo());
+        assert_eq!(universe.occupied_cells(), vec![(1, 1, Cell::ALIVE)]);
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 0);
+        assert!(!universe.can_undo());
+
+        for _ in 0..4 {
+            assert!(universe.redo());
+        }
+        assert!(!universe.redo());
+        assert_eq!(universe.occupied_cells(), stepped);
+
+        // An edit after undoing cannot be redone past.
+        universe.undo();
+        universe.toggle_cell(0, 0);
+        assert!(!universe.can_redo());
+
+        // A resize is undone with the cells it cleared.
+        let cells = universe.occupied_cells();
+        universe.resize(8, 8, 4);
+        assert_eq!((universe.width(), universe.height(), universe.population()), (8, 8, 0));
+        universe.undo();
+        assert_eq!((universe.width(), universe.height(), universe.size()), (16, 16, 8));
+        assert_eq!(universe.occupied_cells(), cells);
+        universe.redo();
+        assert_eq!((universe.width(), universe.size()), (8, 4));
+
+        // Destroying all life on an unbounded plane, even beyond the
+        // viewport.
+        universe.set_topology("P0,0").unwrap();
+        assert!(!universe.can_undo());
+        universe.set_cells(&[(2, 2), (20, 20)]);
+        universe.destroy_all_life();
+        universe.load_rle("3o!", 4, 4).unwrap();
+        universe.undo();
+        universe.undo();
+        assert_eq!(universe.occupied_cells().len(), 2);
+
+        // Loading clears the cells and sets them again, and is undone to the
+        // cells before it.
+        universe.set_topology("T16,16").unwrap();
+        universe.seed_population(8, 8, String::from("glider"), false, false, false, "copy").unwrap();
+        let glider = universe.occupied_cells();
+        let png = universe.to_png(1, false, &[]).unwrap();
+        universe.load_png(&png, 128).unwrap();
+        assert!(universe.undo());
+        assert_eq!(universe.occupied_cells(), glider);
+
+        universe.set_undo_limit(0);
+        universe.toggle_cell(0, 0);
+        assert!(!universe.can_undo());
+    }
+
+    #[test]
+    fn test_fewer_states() {
+        let mut universe = empty_universe(8, 8);
+        universe.set_rule("B2/S/C4").unwrap();
+        universe.set_cells(&[(3, 3), (3, 4)]);
+        universe.step_by(2);
+        assert!(universe.occupied_cells().iter().any(|&(_, _, cell)| cell.state() == 3));
+        universe.set_rule("B3/S23").unwrap();
+        assert!(universe.occupied_cells().iter().all(|&(_, _, cell)| cell.state() < 2));
+        universe.to_png(1, false, &[]).unwrap();
+        universe.record_gif(2, 1, 100, 1, false).unwrap();
+    }
+
+    #[test]
+    fn test_png() {
+        let mut universe = empty_universe(8, 6);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        let image = snapshot::decode(&universe.to_png(3, true, &[]).unwrap()).unwrap();
+        assert_eq!((image.width, image.height), (8 * 4 + 1, 6 * 4 + 1));
+        assert!(universe.to_png(0, false, &[]).is_err());
+
+        let mut copy = empty_universe(8, 6);
+        copy.load_png(&universe.to_png(1, false, &[]).unwrap(), 128).unwrap();
+        assert_eq!(copy.get_cells(), universe.get_cells());
+
+        // Dark cells on a light background load inverted, and pixels
+        // outside the universe are left out.
+        let inverted = universe.to_png(1, false, &[0xffffff, 0x000000]).unwrap();
+        copy.set_width(3);
+        copy.set_height(3);
+        copy.load_png(&inverted, 128).unwrap();
+        assert_eq!(copy.live_cells().len(), 8);
+
+        universe.set_rule("B2/S/C3").unwrap();
+        universe.tick();
+        copy.set_rule("B2/S/C3").unwrap();
+        copy.set_width(8);
+        copy.set_height(6);
+        copy.load_png(&universe.to_png(1, false, &[]).unwrap(), 100).unwrap();
+        assert_eq!(copy.get_cells(), universe.get_cells());
+        assert!(copy.load_png(b"not a png", 100).is_err());
+    }
+
+    #[test]
+    fn test_record_gif() {
+        let mut universe = empty_universe(16, 16);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        let bytes = universe.record_gif(8, 2, 50, 4, true).unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(&bytes[..]).unwrap();
+        // The glider moves two cells in eight generations, so the cropped
+        // frames are five cells square.
+        assert_eq!((decoder.width(), decoder.height()), (10, 10));
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert_eq!(frames, 3);
+        assert_eq!(universe.live_cells(), vec![(3, 4), (4, 5), (5, 3), (5, 4), (5, 5)]);
+
+        assert!(universe.record_gif(8, 2, 50, 0, true).is_err());
+        assert!(universe.record_gif(8, 0, 50, 1, true).is_err());
+    }
+
+    #[test]
+    fn test_svg() {
+        let mut universe = empty_universe(64, 64);
+        let row: Vec<(u32, u32)> = (0..64).map(|col| (10, col)).collect();
+        universe.set_cells(&row);
+        let svg = universe.to_svg(4, true, &[]);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"321\" height=\"321\""));
+        assert!(svg.contains("<path fill=\"#b3ccb5\" d=\"M1 51h319v4h-319z\"/>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_pattern_library() {
+        let mut universe = empty_universe(16, 16);
+        assert_eq!(universe.list_patterns()[0], "block");
+        assert_eq!(universe.pattern_info("gosper-gun").unwrap().period(), 30);
+        assert!(universe.pattern_info("gun").is_err());
+
+        universe.seed_population(5, 5, String::from("glider"), false, false, false, "copy").unwrap();
+        assert_eq!(universe.live_cells(), vec![(4, 5), (5, 6), (6, 4), (6, 5), (6, 6)]);
+        assert_eq!(
+            universe.seed_population(5, 5, String::from("unknown"), false, false, false, "copy"),
+            Err(String::from("unknown pattern \"unknown\""))
+        );
+        assert_eq!(universe.live_cells().len(), 5);
+    }
+
+    #[test]
+    fn test_register_pattern() {
+        let mut universe = empty_universe(16, 16);
+        universe.register_pattern("snake", "#N Snake\nx = 4, y = 2\n2obo$ob2o!").unwrap();
+        assert_eq!(universe.list_patterns().last().unwrap(), "snake");
+        assert_eq!(universe.pattern_info("snake").unwrap().title(), "Snake");
+        assert!(universe.register_pattern("block", "2o$2o!").is_err());
+
+        universe.seed_population(5, 5, String::from("snake"), false, false, false, "copy").unwrap();
+        assert_eq!(universe.live_cells(), vec![(4, 3), (4, 4), (4, 6), (5, 3), (5, 5), (5, 6)]);
+        universe.destroy_all_life();
+        universe.seed_population(5, 5, String::from("snake"), false, false, true, "copy").unwrap();
+        assert_eq!(universe.live_cells(), vec![(3, 4), (3, 5), (4, 4), (5, 5), (6, 4), (6, 5)]);
+
+        let exported = universe.export_patterns();
+        assert_eq!(exported, vec![String::from("snake"), String::from("#N Snake\nx = 4, y = 2\n2obo$ob2o!\n")]);
+        universe.unregister_pattern("snake").unwrap();
+        assert!(universe.seed_population(5, 5, String::from("snake"), false, false, false, "copy").is_err());
+        assert!(universe.export_patterns().is_empty());
+        universe.register_pattern(&exported[0], &exported[1]).unwrap();
+        assert_eq!(universe.export_patterns(), exported);
+    }
+
+    #[test]
+    fn test_seed_transformed() {
+        let mut universe = empty_universe(16, 16);
+        // A vertical flip keeps the glider in the same three rows.
+        universe.seed_population(5, 5, String::from("glider"), false, true, false, "copy").unwrap();
+        assert_eq!(universe.live_cells(), vec![(4, 4), (4, 5), (4, 6), (5, 6), (6, 5)]);
+        universe.destroy_all_life();
+        universe.seed_population(5, 5, String::from("glider"), true, false, false, "copy").unwrap();
+        assert_eq!(universe.live_cells(), vec![(4, 5), (5, 4), (6, 4), (6, 5), (6, 6)]);
+
+        universe.destroy_all_life();
+        universe.seed_transformed(5, 5, "lwss", "rot90", "copy").unwrap();
+        let info = universe.pattern_info("lwss").unwrap();
+        assert_eq!(universe.live_cells().len(), info.cells().len() / 2);
+        let rows: Vec<i64> = universe.live_cells().iter().map(|&(row, _)| row).collect();
+        assert_eq!((rows[0], rows[rows.len() - 1]), (5 - 2, 5 + 2));
+        assert!(universe.seed_transformed(5, 5, "glider", "rot45", "copy").is_err());
+    }
+
+    #[test]
+    fn test_paste_modes() {
+        let mut universe = empty_universe(8, 8);
+        // A blinker across the middle of a block's box.
+        let blinker = |universe: &mut Universe| {
+            universe.destroy_all_life();
+            universe.set_cells(&[(3, 2), (3, 3), (3, 4)]);
+        };
+
+        blinker(&mut universe);
+        universe.seed_population(3, 3, String::from("block"), false, false, false, "xor").unwrap();
+        assert_eq!(universe.live_cells(), vec![(2, 2), (2, 3), (3, 4)]);
+        blinker(&mut universe);
+        universe.seed_transformed(3, 3, "block", "identity", "and_not").unwrap();
+        assert_eq!(universe.live_cells(), vec![(3, 4)]);
+        assert!(universe.seed_population(3, 3, String::from("block"), false, false, false, "nor").is_err());
+
+        // Clipboard contents: the dead cells of an RLE clear cells in copy
+        // mode only.
+        blinker(&mut universe);
+        universe.paste_rle("x = 3, y = 1\nobo!", 3, 2, "or").unwrap();
+        assert_eq!(universe.live_cells(), vec![(3, 2), (3, 3), (3, 4)]);
+        universe.paste_rle("x = 3, y = 1\nobo!", 3, 2, "copy").unwrap();
+        assert_eq!(universe.live_cells(), vec![(3, 2), (3, 4)]);
+        universe.paste_rle("x = 3, y = 1\n3o!", 3, 2, "xor").unwrap();
+        assert_eq!(universe.live_cells(), vec![(3, 3)]);
+        assert!(universe.paste_rle("x = 1, y = 1\nB!", 0, 0, "or").is_err());
+        assert_eq!(universe.rule(), "B3/S23");
+
+        // A huge box is only gone through in the modes that need it.
+        let huge = "x = 100000, y = 100000\no!";
+        universe.paste_rle(huge, 0, 0, "or").unwrap();
+        assert!(universe.paste_rle(huge, 0, 0, "copy").is_err());
+
+        // Pasting across the edge of a torus wraps around.
+        universe.destroy_all_life();
+        universe.paste_rle("2o!", 0, 7, "or").unwrap();
+        assert_eq!(universe.live_cells(), vec![(0, 0), (0, 7)]);
+    }
 }
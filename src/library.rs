@@ -0,0 +1,426 @@
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+use crate::object::{self, MAX_PERIOD};
+use crate::rle::Rle;
+use crate::rule::Rule;
+use crate::Cell;
+
+/// What kind of pattern a library entry is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    Gun,
+    /// A pattern that does not come back to the same shape, or that was
+    /// not checked.
+    Unknown,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Category::StillLife => "still life",
+            Category::Oscillator => "oscillator",
+            Category::Spaceship => "spaceship",
+            Category::Gun => "gun",
+            Category::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A named pattern that can be seeded into a universe.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    name: String,
+    title: String,
+    category: Category,
+    period: u32,
+    author: Option<String>,
+    /// The rule in the header of a registered pattern, if it had one.
+    rule: Option<String>,
+    height: u32,
+    width: u32,
+    cells: Vec<(u32, u32)>,
+}
+
+impl Pattern {
+    /// A pattern of live `cells` given by row and column, sized to fit them.
+    pub fn new(
+        name: &str,
+        title: &str,
+        category: Category,
+        period: u32,
+        author: Option<&str>,
+        cells: Vec<(u32, u32)>,
+    ) -> Pattern {
+        let height = cells.iter().map(|&(row, _)| row + 1).max().unwrap_or(0);
+        let width = cells.iter().map(|&(_, col)| col + 1).max().unwrap_or(0);
+        Pattern {
+            name: name.to_string(),
+            title: title.to_string(),
+            category,
+            period,
+            author: author.map(str::to_string),
+            rule: None,
+            height,
+            width,
+            cells,
+        }
+    }
+
+    /// A pattern from a run length encoded one, titled by its `#N` line
+    /// and otherwise by `name`. Its category and period are found by
+    /// running it under the rule in its header, or Life.
+    pub fn from_rle(name: &str, text: &str) -> Result<Pattern, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(String::from("patterns need a name"));
+        }
+        let rle = Rle::parse(text)?;
+        if let Some(&(_, _, cell)) = rle.cells.iter().find(|&&(_, _, cell)| cell.state() > 1) {
+            return Err(format!(
+                "pattern \"{}\" has cells in state {}, only live cells can be seeded",
+                name,
+                cell.state()
+            ));
+        }
+        if rle.cells.is_empty() {
+            return Err(format!("pattern \"{}\" has no live cells", name));
+        }
+        let rule: Rule = match &rle.rule {
+            Some(rule) => rule.split(':').next().unwrap_or("").parse()?,
+            None => Rule::conway(),
+        };
+
+        let cells: Vec<(i64, i64, Cell)> =
+            rle.cells.iter().map(|&(row, col, cell)| (row as i64, col as i64, cell)).collect();
+        let evolution = if rule.births_from_nothing() {
+            None
+        } else {
+            object::evolve(&rule, &cells, MAX_PERIOD)
+        };
+        let (category, period) = match evolution {
+            Some(evolution) => (evolution.category(), evolution.period),
+            None => (Category::Unknown, 0),
+        };
+
+        let title = rle.name.as_deref().unwrap_or(name);
+        let cells = rle.cells.iter().map(|&(row, col, _)| (row, col)).collect();
+        let mut pattern = Pattern::new(name, title, category, period, rle.author.as_deref(), cells);
+        pattern.rule = rle.rule;
+        Ok(pattern)
+    }
+
+    /// The pattern run length encoded, with its title, author and rule.
+    pub fn to_rle(&self) -> String {
+        Rle {
+            name: Some(self.title.clone()),
+            author: self.author.clone(),
+            rule: self.rule.clone(),
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(|&(row, col)| (row, col, Cell::ALIVE)).collect(),
+            ..Rle::default()
+        }
+        .to_string()
+    }
+
+    pub fn kind(&self) -> Category {
+        self.category
+    }
+
+    /// The row and column of every live cell.
+    pub fn live_cells(&self) -> &[(u32, u32)] {
+        &self.cells
+    }
+}
+
+#[wasm_bindgen]
+impl Pattern {
+    /// The name the pattern is seeded by, such as `lwss`.
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The name to show, such as `Light Weight Spaceship`.
+    pub fn title(&self) -> String {
+        self.title.clone()
+    }
+
+    /// `still life`, `oscillator`, `spaceship`, `gun` or `unknown`.
+    pub fn category(&self) -> String {
+        self.category.to_string()
+    }
+
+    /// The number of generations until the pattern looks the same again,
+    /// 1 for still lifes and 0 for unknown patterns.
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn author(&self) -> Option<String> {
+        self.author.clone()
+    }
+
+    pub fn rule(&self) -> Option<String> {
+        self.rule.clone()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The row and column of every live cell, one after the other.
+    pub fn cells(&self) -> Vec<u32> {
+        self.cells.iter().flat_map(|&(row, col)| vec![row, col]).collect()
+    }
+}
+
+/// The patterns a universe can be seeded with, by name: the built in
+/// patterns followed by the registered ones.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatternLibrary {
+    patterns: Vec<Pattern>,
+    builtins: usize,
+}
+
+impl PatternLibrary {
+    /// The built in patterns.
+    #[rustfmt::skip]
+    pub fn new() -> PatternLibrary {
+        let conway = Some("John Conway");
+        let patterns = vec![
+            Pattern::new("block", "Block", Category::StillLife, 1, None, vec![
+                (0, 0), (0,1),
+                (1, 0), (1, 1)
+            ]),
+            Pattern::new("blinker", "Blinker", Category::Oscillator, 2, conway, vec![
+                (0, 0), (0, 1), (0, 2)
+            ]),
+            Pattern::new("toad", "Toad", Category::Oscillator, 2, Some("Simon Norton"), vec![
+                        (0, 1), (0, 2), (0,3),
+                (1, 0), (1, 1), (1, 2)
+            ]),
+            Pattern::new("beacon", "Beacon", Category::Oscillator, 2, conway, vec![
+                (0, 0), (0, 1),
+                (1, 0),
+                                (2, 3),
+                        (3, 2), (3, 3)
+            ]),
+            Pattern::new("pulsar", "Pulsar", Category::Oscillator, 3, conway, vec![
+                                (0, 2), (0, 3), (0, 4),                         (0, 8), (0, 9), (0, 10),
+                (2, 0),                                 (2, 5),         (2, 7),                                 (2, 12),
+                (3 ,0),                                 (3, 5),         (3, 7),                                 (3, 12),
+                (4, 0),                                 (4, 5),         (4, 7),                                 (4, 12),
+                                (5, 2), (5, 3), (5, 4),                         (5, 8), (5, 9), (5, 10),
+                                (7, 2), (7, 3), (7, 4),                         (7, 8), (7, 9), (7, 10),
+                (8, 0),                                 (8, 5),         (8, 7),                                 (8, 12),
+                (9, 0),                                 (9, 5),         (9, 7),                                 (9, 12),
+                (10,0),                                 (10,5),         (10,7),                                 (10,12),
+                                (12,2), (12,3), (12,4),                         (12,8), (12,9), (12,10)
+            ]),
+            Pattern::new("i-column", "I Column", Category::Oscillator, 15, conway, vec![
+                (0, 0), (0, 1), (0, 2),
+                        (1, 1),
+                        (2, 1),
+                (3, 0), (3, 1), (3, 2),
+
+                (5, 0), (5, 1), (5, 2),
+                (6, 0), (6, 1), (6, 2),
+
+                (8, 0), (8, 1), (8, 2),
+                        (9, 1),
+                        (10,1),
+                (11,0), (11,1), (11,2)
+            ]),
+            Pattern::new("glider", "Glider", Category::Spaceship, 4, Some("Richard K. Guy"), vec![
+                        (0, 1),
+                                (1, 2),
+                (2, 0), (2, 1), (2, 2)
+            ]),
+            Pattern::new("lwss", "Light Weight Spaceship", Category::Spaceship, 4, conway, vec![
+                        (0, 1), (0, 2), (0, 3), (0, 4),
+                (1, 0),                         (1, 4),
+                                                (2, 4),
+                (3, 0),                 (3, 3)
+            ]),
+            Pattern::new("mwss", "Middle Weight Spaceship", Category::Spaceship, 4, conway, vec![
+                        (0, 1), (0, 2), (0, 3), (0, 4), (0, 5),
+                (1, 0),                                 (1, 5),
+                                                        (2, 5),
+                (3, 0),                         (3, 4),
+                                (4, 2)
+            ]),
+            Pattern::new("hwss", "Heavy Weight Spaceship", Category::Spaceship, 4, conway, vec![
+                        (0, 1), (0, 2), (0, 3), (0, 4), (0, 5), (0, 6),
+                (1, 0),                                         (1, 6),
+                                                                (2, 6),
+                (3, 0),                                 (3, 5),
+                                (4, 2), (4, 3)
+            ]),
+            Pattern::new("gosper-gun", "Gosper Glider Gun", Category::Gun, 30, Some("Bill Gosper"), vec![
+                                                                                (0, 24),
+                                                                        (1, 22), (1, 24),
+                (2, 12), (2, 13), (2, 20), (2, 21), (2, 34), (2, 35),
+                (3, 11), (3, 15), (3, 20), (3, 21), (3, 34), (3, 35),
+                (4, 0), (4, 1), (4, 10), (4, 16), (4, 20), (4, 21),
+                (5, 0), (5, 1), (5, 10), (5, 14), (5, 16), (5, 17), (5, 22), (5, 24),
+                (6, 10), (6, 16), (6, 24),
+                (7, 11), (7, 15),
+                (8, 12), (8, 13)
+            ]),
+        ];
+        PatternLibrary {
+            builtins: patterns.len(),
+            patterns,
+        }
+    }
+
+    /// Every pattern, in the order they were added.
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    /// The registered patterns, in the order they were first registered.
+    pub fn registered(&self) -> &[Pattern] {
+        &self.patterns[self.builtins..]
+    }
+
+    /// Add a pattern, replacing the registered pattern of the same name.
+    /// Built in patterns cannot be replaced.
+    pub fn register(&mut self, pattern: Pattern) -> Result<(), String> {
+        match self.patterns.iter().position(|other| other.name == pattern.name) {
+            Some(idx) if idx < self.builtins => {
+                Err(format!("\"{}\" is a built in pattern and cannot be replaced", pattern.name))
+            }
+            Some(idx) => {
+                self.patterns[idx] = pattern;
+                Ok(())
+            }
+            None => {
+                self.patterns.push(pattern);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Result<(), String> {
+        match self.patterns.iter().position(|pattern| pattern.name == name) {
+            Some(idx) if idx < self.builtins => {
+                Err(format!("\"{}\" is a built in pattern and cannot be removed", name))
+            }
+            Some(idx) => {
+                self.patterns.remove(idx);
+                Ok(())
+            }
+            None => Err(format!("unknown pattern \"{}\"", name)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Pattern, String> {
+        self.patterns
+            .iter()
+            .find(|pattern| pattern.name == name)
+            .ok_or_else(|| format!("unknown pattern \"{}\"", name))
+    }
+}
+
+impl Default for PatternLibrary {
+    fn default() -> PatternLibrary {
+        PatternLibrary::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{evolve, normalize};
+    use crate::rule::Rule;
+    use crate::Cell;
+
+    #[test]
+    fn test_library() {
+        let library = PatternLibrary::new();
+        assert_eq!(library.patterns().len(), 11);
+        assert_eq!(library.patterns()[0].name(), "block");
+        let gun = library.get("gosper-gun").unwrap();
+        assert_eq!((gun.category(), gun.period(), gun.width(), gun.height()), (String::from("gun"), 30, 36, 9));
+        let lwss = library.get("lwss").unwrap();
+        assert_eq!((lwss.height(), lwss.width()), (4, 5));
+        assert_eq!(lwss.category(), "spaceship");
+        assert_eq!(lwss.author().as_deref(), Some("John Conway"));
+        assert_eq!(&lwss.cells()[..4], &[0, 1, 0, 2]);
+        assert_eq!(library.get("pulsar").unwrap().height(), 13);
+        assert_eq!(library.get("blinker").unwrap().height(), 1);
+        assert_eq!(library.get("gosper").unwrap_err(), "unknown pattern \"gosper\"");
+
+        // Every pattern has the category and period it claims.
+        for pattern in library.patterns() {
+            let cells: Vec<(i64, i64, Cell)> = pattern
+                .live_cells()
+                .iter()
+                .map(|&(row, col)| (row as i64, col as i64, Cell::ALIVE))
+                .collect();
+            if pattern.kind() == Category::Gun {
+                let evolution = evolve(&Rule::conway(), &cells, pattern.period());
+                assert_eq!(evolution, None, "{}", pattern.name());
+                continue;
+            }
+            let evolution = evolve(&Rule::conway(), &cells, 100).unwrap();
+            assert_eq!(evolution.period, pattern.period(), "{}", pattern.name());
+            let moves = evolution.dy != 0 || evolution.dx != 0;
+            let category = match (moves, evolution.period) {
+                (true, _) => Category::Spaceship,
+                (false, 1) => Category::StillLife,
+                (false, _) => Category::Oscillator,
+            };
+            assert_eq!(category, pattern.kind(), "{}", pattern.name());
+            assert_eq!(normalize(&cells).0, cells);
+        }
+    }
+
+    #[test]
+    fn test_register() {
+        let mut library = PatternLibrary::new();
+        let glider = library.get("glider").unwrap().to_rle();
+        assert_eq!(glider, "#N Glider\n#O Richard K. Guy\nx = 3, y = 3\nbo$2bo$3o!\n");
+
+        let ship = Pattern::from_rle("ship", &glider).unwrap();
+        assert_eq!((ship.title(), ship.kind(), ship.period()), (String::from("Glider"), Category::Spaceship, 4));
+        library.register(ship).unwrap();
+        let beehive = Pattern::from_rle("beehive", "x = 4, y = 3, rule = B3/S23:T10,10\nb2o$o2bo$b2o!").unwrap();
+        assert_eq!((beehive.title(), beehive.kind(), beehive.period()), (String::from("beehive"), Category::StillLife, 1));
+        assert_eq!(beehive.rule().as_deref(), Some("B3/S23:T10,10"));
+        library.register(beehive).unwrap();
+        let other = Pattern::from_rle("r", "o!").unwrap();
+        assert_eq!((other.kind(), other.period()), (Category::Unknown, 0));
+        library.register(other).unwrap();
+        let names: Vec<String> = library.registered().iter().map(Pattern::name).collect();
+        assert_eq!(names, vec!["ship", "beehive", "r"]);
+
+        let replacement = Pattern::from_rle("ship", "#N Block\n2o$2o!").unwrap();
+        library.register(replacement).unwrap();
+        assert_eq!(library.get("ship").unwrap().kind(), Category::StillLife);
+        assert_eq!(library.registered().len(), 3);
+
+        library.unregister("ship").unwrap();
+        assert!(library.get("ship").is_err());
+        assert!(library.unregister("ship").is_err());
+        assert!(library.unregister("glider").is_err());
+        assert!(library.register(Pattern::from_rle("glider", &glider).unwrap()).is_err());
+
+        assert!(Pattern::from_rle(" ", &glider).is_err());
+        assert!(Pattern::from_rle("empty", "x = 0, y = 0\n!").is_err());
+        assert!(Pattern::from_rle("dying", "x = 2, y = 1, rule = /2/3\nAB!").is_err());
+        assert!(Pattern::from_rle("bad", "bo?!").is_err());
+    }
+}
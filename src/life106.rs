@@ -0,0 +1,56 @@
+/// The header line of the Life 1.06 format.
+const HEADER: &str = "#Life 1.06";
+
+/// Parse a pattern in the Life 1.06 format: a `#Life 1.06` header followed
+/// by the `x y` coordinates of each live cell, one per line, which may be
+/// negative. Returns the row and column of each live cell.
+pub fn parse(text: &str) -> Result<Vec<(i64, i64)>, String> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    match lines.next() {
+        Some(header) if header.eq_ignore_ascii_case(HEADER) => {}
+        _ => return Err(format!("invalid Life 1.06 pattern: expected a \"{}\" header", HEADER)),
+    }
+    let mut cells = Vec::new();
+    for line in lines {
+        if line.starts_with('#') {
+            continue;
+        }
+        let coordinates: Vec<Option<i64>> = line.split_whitespace().map(|n| n.parse().ok()).collect();
+        match coordinates.as_slice() {
+            [Some(x), Some(y)] => cells.push((*y, *x)),
+            _ => {
+                return Err(format!(
+                    "invalid Life 1.06 pattern: expected \"x y\" coordinates, found \"{}\"",
+                    line
+                ))
+            }
+        }
+    }
+    Ok(cells)
+}
+
+/// Write the row and column of each live cell in the Life 1.06 format.
+pub fn write(cells: &[(i64, i64)]) -> String {
+    let mut text = format!("{}\n", HEADER);
+    for &(row, col) in cells {
+        text.push_str(&format!("{} {}\n", col, row));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_write() {
+        let text = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+        let cells = parse(text).unwrap();
+        assert_eq!(cells, vec![(-1, 0), (0, 1), (1, -1), (1, 0), (1, 1)]);
+        assert_eq!(write(&cells), text);
+
+        assert!(parse("0 0\n").is_err());
+        assert!(parse("#Life 1.06\n0\n").is_err());
+        assert!(parse("#Life 1.06\n0 a\n").is_err());
+    }
+}
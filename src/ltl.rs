@@ -0,0 +1,321 @@
+use std::fmt;
+
+/// The shape of a Larger than Life neighborhood.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// Every cell within the square of the given range.
+    Moore,
+    /// Every cell within the given Manhattan distance.
+    VonNeumann,
+    /// Every cell whose center lies within a circle of the given range plus
+    /// one half.
+    Circular,
+}
+
+/// A Larger than Life rule, in which a cell's fate is decided by the number
+/// of live cells in an extended neighborhood falling within the birth or
+/// survival interval.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LargerThanLife {
+    pub range: u32,
+    pub neighborhood: Neighborhood,
+    /// Whether the cell itself counts towards its neighborhood's population.
+    pub middle: bool,
+    pub birth: (u32, u32),
+    pub survival: (u32, u32),
+}
+
+/// The largest range accepted by `LargerThanLife::parse`, matching Golly.
+const MAX_RANGE: u32 = 500;
+
+fn parse_number(rule: &str, field: &str, value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| {
+        format!(
+            "invalid rule \"{}\": expected a number for '{}', found \"{}\"",
+            rule, field, value
+        )
+    })
+}
+
+fn parse_interval(rule: &str, field: &str, value: &str) -> Result<(u32, u32), String> {
+    let (min, max) = match value.find("..") {
+        Some(split) => (&value[..split], &value[split + 2..]),
+        None => (value, value),
+    };
+    let (min, max) = (parse_number(rule, field, min)?, parse_number(rule, field, max)?);
+    if min > max {
+        return Err(format!(
+            "invalid rule \"{}\": '{}' interval {}..{} is empty",
+            rule, field, min, max
+        ));
+    }
+    Ok((min, max))
+}
+
+impl LargerThanLife {
+    /// Parse a rule in Golly's `R5,C0,M1,S34..58,B34..45,NM` notation,
+    /// returning it with its number of cell states. `C` and `M` default to 0
+    /// and `N` to a Moore neighborhood when they are left out.
+    pub fn parse(rule: &str) -> Result<(LargerThanLife, u8), String> {
+        let mut range = None;
+        let mut states = 0;
+        let mut middle = false;
+        let mut birth = None;
+        let mut survival = None;
+        let mut neighborhood = Neighborhood::Moore;
+
+        for field in rule.split(',').map(str::trim) {
+            let mut chars = field.chars();
+            let name = chars.next().map(|c| c.to_ascii_uppercase());
+            let value = chars.as_str();
+            match name {
+                Some('R') => range = Some(parse_number(rule, "R", value)?),
+                Some('C') => {
+                    states = match parse_number(rule, "C", value)? {
+                        states if states <= 255 => states,
+                        _ => {
+                            return Err(format!(
+                                "invalid rule \"{}\": number of states must be at most 255",
+                                rule
+                            ))
+                        }
+                    }
+                }
+                Some('M') => {
+                    middle = match value {
+                        "0" => false,
+                        "1" => true,
+                        _ => {
+                            return Err(format!(
+                                "invalid rule \"{}\": 'M' must be 0 or 1",
+                                rule
+                            ))
+                        }
+                    }
+                }
+                Some('S') => survival = Some(parse_interval(rule, "S", value)?),
+                Some('B') => birth = Some(parse_interval(rule, "B", value)?),
+                Some('N') => {
+                    neighborhood = match value.to_ascii_uppercase().as_str() {
+                        "M" => Neighborhood::Moore,
+                        "N" => Neighborhood::VonNeumann,
+                        "C" => Neighborhood::Circular,
+                        _ => {
+                            return Err(format!(
+                                "invalid rule \"{}\": unknown neighborhood \"{}\", expected NM, NN or NC",
+                                rule, value
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "invalid rule \"{}\": unexpected field \"{}\"",
+                        rule, field
+                    ))
+                }
+            }
+        }
+
+        let range = match range {
+            Some(range) if (1..=MAX_RANGE).contains(&range) => range,
+            Some(_) => {
+                return Err(format!(
+                    "invalid rule \"{}\": range must be between 1 and {}",
+                    rule, MAX_RANGE
+                ))
+            }
+            None => return Err(format!("invalid rule \"{}\": 'R' is required", rule)),
+        };
+        let (birth, survival) = match (birth, survival) {
+            (Some(birth), Some(survival)) => (birth, survival),
+            _ => {
+                return Err(format!(
+                    "invalid rule \"{}\": both 'B' and 'S' intervals are required",
+                    rule
+                ))
+            }
+        };
+
+        let ltl = LargerThanLife {
+            range,
+            neighborhood,
+            middle,
+            birth,
+            survival,
+        };
+        Ok((ltl, states.max(2) as u8))
+    }
+
+    /// How many columns either side of a cell the neighborhood reaches in the
+    /// row `dy` rows above or below it.
+    fn reach(&self, dy: u32) -> u32 {
+        let range = self.range;
+        match self.neighborhood {
+            Neighborhood::Moore => range,
+            Neighborhood::VonNeumann => range - dy,
+            Neighborhood::Circular => {
+                let limit = range * range + range - dy * dy;
+                let mut reach = (limit as f64).sqrt() as u32;
+                while reach * reach > limit {
+                    reach -= 1;
+                }
+                while (reach + 1) * (reach + 1) <= limit {
+                    reach += 1;
+                }
+                reach
+            }
+        }
+    }
+
+    /// Count the live cells in the neighborhood of every cell of a `width` by
+    /// `height` universe. `alive` is called with coordinates up to `range`
+    /// outside of the universe and decides how its edges behave.
+    ///
+    /// Moore neighborhoods are summed from a summed-area table in constant
+    /// time per cell; other shapes sum one row prefix sum per row.
+    pub fn count_neighbors<F>(&self, width: u32, height: u32, alive: F) -> Vec<u32>
+    where
+        F: Fn(i64, i64) -> bool,
+    {
+        let range = self.range as usize;
+        let (width, height) = (width as usize, height as usize);
+        let padded_width = width + 2 * range;
+        let padded_height = height + 2 * range;
+
+        // prefix[y][x] is the number of live cells in padded row y before
+        // column x. For Moore neighborhoods it also includes every row
+        // before y, making it a summed-area table.
+        let stride = padded_width + 1;
+        let mut prefix = vec![0u32; stride * (padded_height + 1)];
+        for y in 0..padded_height {
+            let mut row_sum = 0;
+            for x in 0..padded_width {
+                row_sum += alive(y as i64 - range as i64, x as i64 - range as i64) as u32;
+                prefix[(y + 1) * stride + x + 1] = row_sum;
+            }
+            if self.neighborhood == Neighborhood::Moore {
+                for x in 0..=padded_width {
+                    prefix[(y + 1) * stride + x] += prefix[y * stride + x];
+                }
+            }
+        }
+
+        let reaches: Vec<usize> = (0..=self.range).map(|dy| self.reach(dy) as usize).collect();
+        let mut counts = vec![0; width * height];
+        for row in 0..height {
+            for col in 0..width {
+                let (y, x) = (row + range, col + range);
+                let mut count = if self.neighborhood == Neighborhood::Moore {
+                    let (top, bottom) = (y - range, y + range + 1);
+                    let (left, right) = (x - range, x + range + 1);
+                    prefix[bottom * stride + right] + prefix[top * stride + left]
+                        - prefix[top * stride + right]
+                        - prefix[bottom * stride + left]
+                } else {
+                    let mut count = 0;
+                    for row_y in y - range..=y + range {
+                        let reach = reaches[y.abs_diff(row_y)];
+                        let row_start = (row_y + 1) * stride;
+                        count += prefix[row_start + x + reach + 1] - prefix[row_start + x - reach];
+                    }
+                    count
+                };
+                if !self.middle && alive(row as i64, col as i64) {
+                    count -= 1;
+                }
+                counts[row * width + col] = count;
+            }
+        }
+
+        counts
+    }
+
+    /// Whether a live (`alive == true`) or dead cell with `count` live cells
+    /// in its neighborhood is alive in the next generation.
+    pub fn lives(&self, alive: bool, count: u32) -> bool {
+        let (min, max) = if alive { self.survival } else { self.birth };
+        min <= count && count <= max
+    }
+
+    pub fn write(&self, f: &mut fmt::Formatter, states: u8) -> fmt::Result {
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.range,
+            if states > 2 { states } else { 0 },
+            self.middle as u8,
+            self.survival.0,
+            self.survival.1,
+            self.birth.0,
+            self.birth.1,
+            match self.neighborhood {
+                Neighborhood::Moore => 'M',
+                Neighborhood::VonNeumann => 'N',
+                Neighborhood::Circular => 'C',
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let (bosco, states) = LargerThanLife::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!(states, 2);
+        assert_eq!(bosco.range, 5);
+        assert!(bosco.middle);
+        assert_eq!(bosco.survival, (34, 58));
+        assert_eq!(bosco.birth, (34, 45));
+        assert_eq!(bosco.neighborhood, Neighborhood::Moore);
+
+        let (ltl, states) = LargerThanLife::parse("r2,c3,m0,s2..3,b3,nn").unwrap();
+        assert_eq!(states, 3);
+        assert_eq!(ltl.birth, (3, 3));
+        assert_eq!(ltl.neighborhood, Neighborhood::VonNeumann);
+
+        assert!(LargerThanLife::parse("R0,S1..2,B1..2").is_err());
+        assert!(LargerThanLife::parse("R2,S3..1,B1..2").is_err());
+        assert!(LargerThanLife::parse("R2,S1..2").is_err());
+        assert!(LargerThanLife::parse("R2,S1..2,B1..2,NX").is_err());
+    }
+
+    #[test]
+    fn test_count_neighbors_matches_direct_count() {
+        let (width, height) = (13, 11);
+        let alive = |row: i64, col: i64| {
+            let (row, col) = (row.rem_euclid(height), col.rem_euclid(width));
+            (row * 7 + col * 3) % 5 == 0 || (row + col) % 4 == 1
+        };
+        for &(neighborhood, within) in &[
+            (Neighborhood::Moore, (|_dy: i64, _dx: i64| true) as fn(i64, i64) -> bool),
+            (Neighborhood::VonNeumann, |dy: i64, dx: i64| dy.abs() + dx.abs() <= 3),
+            (Neighborhood::Circular, |dy: i64, dx: i64| dy * dy + dx * dx <= 12),
+        ] {
+            let ltl = LargerThanLife {
+                range: 3,
+                neighborhood,
+                middle: false,
+                birth: (0, 0),
+                survival: (0, 0),
+            };
+            let counts = ltl.count_neighbors(width as u32, height as u32, alive);
+            for row in 0..height {
+                for col in 0..width {
+                    let mut expected = 0;
+                    for dy in -3..=3 {
+                        for dx in -3..=3 {
+                            if (dy, dx) != (0, 0) && within(dy, dx) {
+                                expected += alive(row + dy, col + dx) as u32;
+                            }
+                        }
+                    }
+                    assert_eq!(counts[(row * width + col) as usize], expected, "{:?}", neighborhood);
+                }
+            }
+        }
+    }
+}
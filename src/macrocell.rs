@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::Cell;
+
+/// The most cells a pattern may have to be loaded cell by cell rather than
+/// as a quadtree.
+pub const MAX_CELLS: u64 = 1 << 24;
+
+/// The deepest level of a quadtree, whose `2^62` cells wide square still has
+/// every position and its size fit in an `i64`.
+pub const MAX_LEVEL: u8 = 62;
+
+/// A node of a macrocell quadtree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Node {
+    /// An 8x8 block of a two-state pattern, one byte per row with bit `col`
+    /// set for live cells.
+    Leaf([u8; 8]),
+    /// A 2x2 block of a multi-state pattern: the states of the north west,
+    /// north east, south west and south east cells.
+    States([u8; 4]),
+    /// A square of `2^level` cells per side made of four squares of half
+    /// the size, in the same order, referred to by their number. 0 refers
+    /// to an empty square.
+    Branch(u8, [usize; 4]),
+}
+
+impl Node {
+    pub(crate) fn level(self) -> u8 {
+        match self {
+            Node::Leaf(_) => 3,
+            Node::States(_) => 1,
+            Node::Branch(level, _) => level,
+        }
+    }
+}
+
+/// A pattern in Golly's macrocell format, which stores the quadtree of a
+/// pattern with every repeated square written once, so that huge regular
+/// patterns stay small:
+///
+/// ```text
+/// [M2] (golly 2.0)
+/// #R B3/S23
+/// .*$..*$***$
+/// 4 1 0 0 0
+/// ```
+///
+/// Each line after the header describes a node, numbered from 1: two-state
+/// patterns have 8x8 leaves written as rows of `.` and `*` ended by `$`,
+/// multi-state patterns have 2x2 leaves written as `1` and four states, and
+/// every other node is its level followed by the numbers of its four
+/// children. The last node is the whole pattern, which is centered on the
+/// origin.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Macrocell {
+    pub rule: Option<String>,
+    /// The nodes, node `n` being at index `n - 1`, children before parents.
+    pub(crate) nodes: Vec<Node>,
+    /// The number of the root node, 0 for an empty pattern.
+    pub(crate) root: usize,
+}
+
+fn parse_leaf(line: &str) -> Result<Node, String> {
+    let mut rows = [0u8; 8];
+    let (mut row, mut col) = (0, 0);
+    for c in line.chars() {
+        match c {
+            '$' => {
+                row += 1;
+                col = 0;
+            }
+            '.' | '*' => {
+                if col == 8 {
+                    return Err(format!("invalid macrocell leaf \"{}\": rows are 8 cells long", line));
+                }
+                if row < 8 && c == '*' {
+                    rows[row] |= 1 << col;
+                }
+                col += 1;
+            }
+            _ => return Err(format!("invalid macrocell leaf \"{}\"", line)),
+        }
+        // Only the `$` ending the last row may follow it.
+        if row > 8 || row == 8 && col > 0 {
+            return Err(format!("invalid macrocell leaf \"{}\": leaves are 8 rows high", line));
+        }
+    }
+    Ok(Node::Leaf(rows))
+}
+
+impl Macrocell {
+    /// The level of the root node: the pattern is `2^level` cells wide.
+    pub fn level(&self) -> u8 {
+        match self.root {
+            0 => 0,
+            root => self.nodes[root - 1].level(),
+        }
+    }
+
+    /// The highest cell state in the pattern.
+    pub fn max_state(&self) -> u8 {
+        self.nodes
+            .iter()
+            .map(|node| match node {
+                Node::Leaf(rows) => rows.iter().any(|&bits| bits != 0) as u8,
+                Node::States(states) => states.iter().cloned().max().unwrap_or(0),
+                Node::Branch(..) => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Add a node, returning its number.
+    pub(crate) fn add(&mut self, node: Node) -> usize {
+        self.nodes.push(node);
+        self.nodes.len()
+    }
+
+    pub fn parse(text: &str) -> Result<Macrocell, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        match lines.next() {
+            Some(header) if header.starts_with("[M2]") => {}
+            _ => return Err(String::from("invalid macrocell pattern: expected a \"[M2]\" header")),
+        }
+        let mut macrocell = Macrocell::default();
+        for line in lines {
+            if let Some(comment) = line.strip_prefix("#R") {
+                macrocell.rule = Some(comment.trim().to_string());
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with(['.', '*', '$']) {
+                macrocell.add(parse_leaf(line)?);
+                continue;
+            }
+
+            let numbers: Vec<Option<usize>> = line.split_whitespace().map(|n| n.parse().ok()).collect();
+            let (level, children) = match numbers.as_slice() {
+                [Some(level), Some(a), Some(b), Some(c), Some(d)] => (*level, [*a, *b, *c, *d]),
+                _ => return Err(format!("invalid macrocell node \"{}\"", line)),
+            };
+            if level > MAX_LEVEL as usize {
+                return Err(format!(
+                    "invalid macrocell node \"{}\": levels above {} are not supported",
+                    line, MAX_LEVEL
+                ));
+            }
+            let node = match level {
+                1 if children.iter().all(|&state| state <= 255) => {
+                    Node::States(children.map(|state| state as u8))
+                }
+                2.. => {
+                    for &child in &children {
+                        let child_level = match child {
+                            0 => level as u8 - 1,
+                            child if child <= macrocell.nodes.len() => macrocell.nodes[child - 1].level(),
+                            _ => {
+                                return Err(format!(
+                                    "invalid macrocell node \"{}\": node {} is not defined yet",
+                                    line, child
+                                ))
+                            }
+                        };
+                        if child_level + 1 != level as u8 {
+                            return Err(format!(
+                                "invalid macrocell node \"{}\": node {} is not at level {}",
+                                line,
+                                child,
+                                level - 1
+                            ));
+                        }
+                    }
+                    Node::Branch(level as u8, children)
+                }
+                _ => return Err(format!("invalid macrocell node \"{}\"", line)),
+            };
+            macrocell.add(node);
+        }
+        macrocell.root = macrocell.nodes.len();
+        Ok(macrocell)
+    }
+
+    /// Build the quadtree of the cells that are not dead among `cells`, given
+    /// by row and column relative to the center of the pattern. Multi-state
+    /// patterns are written with 2x2 leaves.
+    pub fn from_cells(cells: &[(i64, i64, Cell)], multi_state: bool) -> Macrocell {
+        let cells: Vec<(i64, i64, Cell)> =
+            cells.iter().cloned().filter(|&(_, _, cell)| cell != Cell::DEAD).collect();
+        let mut level = if multi_state { 1 } else { 3 };
+        let fits = |level: u8| {
+            let half = 1i64 << (level - 1);
+            cells
+                .iter()
+                .all(|&(row, col, _)| row >= -half && row < half && col >= -half && col < half)
+        };
+        while !fits(level) {
+            level += 1;
+        }
+
+        let mut macrocell = Macrocell::default();
+        let mut lookup = HashMap::new();
+        let half = 1i64 << (level - 1);
+        macrocell.root = macrocell.build(&mut lookup, multi_state, level, (-half, -half), cells);
+        macrocell
+    }
+
+    /// Add the node for the `2^level` cells wide square whose top left cell
+    /// is at `corner`, holding `cells`, returning its number.
+    fn build(
+        &mut self,
+        lookup: &mut HashMap<Node, usize>,
+        multi_state: bool,
+        level: u8,
+        corner: (i64, i64),
+        cells: Vec<(i64, i64, Cell)>,
+    ) -> usize {
+        if cells.is_empty() {
+            return 0;
+        }
+        let (top, left) = corner;
+        let node = if !multi_state && level == 3 {
+            let mut rows = [0u8; 8];
+            for (row, col, _) in cells {
+                rows[(row - top) as usize] |= 1 << (col - left);
+            }
+            Node::Leaf(rows)
+        } else if multi_state && level == 1 {
+            let mut states = [0u8; 4];
+            for (row, col, cell) in cells {
+                states[((row - top) * 2 + col - left) as usize] = cell.state();
+            }
+            Node::States(states)
+        } else {
+            let half = 1i64 << (level - 1);
+            let mut quadrants = vec![Vec::new(); 4];
+            for cell in cells {
+                let (row, col, _) = cell;
+                quadrants[(row >= top + half) as usize * 2 + (col >= left + half) as usize].push(cell);
+            }
+            let mut children = [0; 4];
+            for (quadrant, cells) in quadrants.into_iter().enumerate() {
+                let corner = (top + (quadrant / 2) as i64 * half, left + (quadrant % 2) as i64 * half);
+                children[quadrant] = self.build(lookup, multi_state, level - 1, corner, cells);
+            }
+            Node::Branch(level, children)
+        };
+        if let Some(&number) = lookup.get(&node) {
+            return number;
+        }
+        let number = self.add(node);
+        lookup.insert(node, number);
+        number
+    }
+
+    /// The number of cells that are not dead, counted node by node without
+    /// visiting them.
+    pub fn population(&self) -> u64 {
+        let mut populations: Vec<u64> = Vec::with_capacity(self.nodes.len() + 1);
+        populations.push(0);
+        for node in &self.nodes {
+            let population = match node {
+                Node::Leaf(rows) => rows.iter().map(|bits| bits.count_ones() as u64).sum(),
+                Node::States(states) => states.iter().filter(|&&state| state != 0).count() as u64,
+                Node::Branch(_, children) => children
+                    .iter()
+                    .fold(0u64, |sum, &child| sum.saturating_add(populations[child])),
+            };
+            populations.push(population);
+        }
+        populations[self.root]
+    }
+
+    /// The row, column and state of every cell that is not dead, relative
+    /// to the center of the pattern.
+    pub fn cells(&self) -> Vec<(i64, i64, Cell)> {
+        let mut cells = Vec::new();
+        if self.root != 0 {
+            let half = 1i64 << (self.level() - 1);
+            self.collect_cells(self.root, (-half, -half), &mut cells);
+        }
+        cells
+    }
+
+    fn collect_cells(&self, number: usize, corner: (i64, i64), cells: &mut Vec<(i64, i64, Cell)>) {
+        if number == 0 {
+            return;
+        }
+        let (top, left) = corner;
+        match self.nodes[number - 1] {
+            Node::Leaf(rows) => {
+                for (row, bits) in rows.iter().enumerate() {
+                    for col in (0..8).filter(|col| bits >> col & 1 != 0) {
+                        cells.push((top + row as i64, left + col as i64, Cell::ALIVE));
+                    }
+                }
+            }
+            Node::States(states) => {
+                for (quadrant, &state) in states.iter().enumerate() {
+                    if state != 0 {
+                        let (row, col) = ((quadrant / 2) as i64, (quadrant % 2) as i64);
+                        cells.push((top + row, left + col, Cell::new(state)));
+                    }
+                }
+            }
+            Node::Branch(level, children) => {
+                let half = 1i64 << (level - 1);
+                for (quadrant, &child) in children.iter().enumerate() {
+                    let corner = (top + (quadrant / 2) as i64 * half, left + (quadrant % 2) as i64 * half);
+                    self.collect_cells(child, corner, cells);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Macrocell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[M2] (wasm-game-of-life)")?;
+        if let Some(rule) = &self.rule {
+            writeln!(f, "#R {}", rule)?;
+        }
+        for node in &self.nodes {
+            match node {
+                Node::Leaf(rows) => {
+                    let last = rows.iter().rposition(|&bits| bits != 0).unwrap_or(0);
+                    for &bits in &rows[..=last] {
+                        let row: String = (0..8)
+                            .map(|col| if bits >> col & 1 != 0 { '*' } else { '.' })
+                            .collect();
+                        write!(f, "{}$", row.trim_end_matches('.'))?;
+                    }
+                    writeln!(f)?;
+                }
+                Node::States([a, b, c, d]) => writeln!(f, "1 {} {} {} {}", a, b, c, d)?,
+                Node::Branch(level, [a, b, c, d]) => writeln!(f, "{} {} {} {} {}", level, a, b, c, d)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_write() {
+        let text = "[M2] (wasm-game-of-life)\n#R B3/S23\n.*$..*$***$\n4 0 0 0 1\n5 0 0 0 2\n";
+        let glider = Macrocell::parse(text).unwrap();
+        assert_eq!(glider.rule.as_deref(), Some("B3/S23"));
+        assert_eq!(glider.level(), 5);
+        let mut cells = glider.cells();
+        cells.sort();
+        let alive = |row, col| (row, col, Cell::ALIVE);
+        assert_eq!(cells, vec![alive(8, 9), alive(9, 10), alive(10, 8), alive(10, 9), alive(10, 10)]);
+        assert_eq!(glider.to_string(), text);
+
+        // Rebuilding from the cells finds the smallest tree centered on the
+        // origin.
+        let rebuilt = Macrocell::from_cells(&cells, false);
+        assert_eq!(rebuilt.level(), 5);
+        let mut rebuilt_cells = rebuilt.cells();
+        rebuilt_cells.sort();
+        assert_eq!(rebuilt_cells, cells);
+
+        let multi = Macrocell::from_cells(&[(-1, -1, Cell::new(2)), (0, 0, Cell::ALIVE), (5, 1, Cell::new(2))], true);
+        assert_eq!(multi.level(), 4);
+        assert_eq!(multi.max_state(), 2);
+        assert_eq!(Macrocell::parse(&multi.to_string()).unwrap(), multi);
+        assert!(multi.to_string().contains("\n1 0 0 0 2\n"));
+
+        assert!(Macrocell::parse("#R B3/S23\n").is_err());
+        assert!(Macrocell::parse("[M2]\n4 1 0 0 0\n").is_err());
+        assert!(Macrocell::parse("[M2]\n.*$\n5 1 0 0 0\n").is_err());
+        assert!(Macrocell::parse("[M2]\n.........*$\n").is_err());
+        // Leaves are 8 rows high, whatever their rows hold.
+        assert!(Macrocell::parse("[M2]\n$$$$$$$*$\n").is_ok());
+        assert!(Macrocell::parse("[M2]\n$$$$$$$$*$\n").is_err());
+        assert!(Macrocell::parse("[M2]\n$$$$$$$$.$\n").is_err());
+        assert!(Macrocell::parse("[M2]\n*$$$$$$$$$\n").is_err());
+        assert!(Macrocell::parse("[M2]\n1 256 0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn test_shared_nodes() {
+        // Four blocks, one in each quadrant of a level 4 square, share a
+        // single leaf.
+        let mut cells = Vec::new();
+        for &(row, col) in &[(-8, -8), (-8, 0), (0, -8), (0, 0)] {
+            for &(dy, dx) in &[(3, 3), (3, 4), (4, 3), (4, 4)] {
+                cells.push((row + dy, col + dx, Cell::ALIVE));
+            }
+        }
+        let blocks = Macrocell::from_cells(&cells, false);
+        assert_eq!(blocks.nodes.len(), 2);
+        assert_eq!(blocks.nodes[1], Node::Branch(4, [1, 1, 1, 1]));
+        assert_eq!(blocks.population(), 16);
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::library::Category;
+use crate::plane::SparsePlane;
+use crate::rule::Rule;
+use crate::Cell;
+
+/// The longest period looked for when an object is run on its own.
+pub const MAX_PERIOD: u32 = 1024;
+
+/// The position and state of the cells of a pattern that are not dead.
+pub type Cells = Vec<(i64, i64, Cell)>;
+
+/// The cells of `cells` connected to `start` through chains of cells that
+/// touch, including diagonally.
+pub fn connected(cells: &HashSet<(i64, i64)>, start: (i64, i64)) -> Vec<(i64, i64)> {
+    let mut object = Vec::new();
+    if !cells.contains(&start) {
+        return object;
+    }
+    let mut seen = HashSet::new();
+    let mut stack = vec![start];
+    seen.insert(start);
+    while let Some((row, col)) = stack.pop() {
+        object.push((row, col));
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let neighbor = (row + dy, col + dx);
+                if cells.contains(&neighbor) && seen.insert(neighbor) {
+                    stack.push(neighbor);
+                }
+            }
+        }
+    }
+    object.sort_unstable();
+    object
+}
+
+/// The cells reached from one cell by following cells close to each other,
+/// possibly across the edges of a torus.
+pub struct Cluster {
+    /// Each cell with a position next to the others, differing from the
+    /// cell's own where the cluster crosses an edge.
+    pub positions: HashMap<(i64, i64), (i64, i64)>,
+    /// Whether some cell was reached at two positions, when the cluster
+    /// wraps all the way around.
+    pub wraps_around: bool,
+}
+
+/// Follow the cells of `cells` within `reach` rows and columns of each
+/// other from `start`. `wrap` gives the cell a position refers to, or `None`
+/// when it is outside the universe.
+pub fn cluster<F>(cells: &HashSet<(i64, i64)>, start: (i64, i64), reach: i64, wrap: F) -> Cluster
+where
+    F: Fn(i64, i64) -> Option<(i64, i64)>,
+{
+    let mut positions = HashMap::new();
+    let mut wraps_around = false;
+    if !cells.contains(&start) {
+        return Cluster { positions, wraps_around };
+    }
+    let mut stack = vec![start];
+    positions.insert(start, start);
+    while let Some(cell) = stack.pop() {
+        let (row, col) = positions[&cell];
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let position = (row + dy, col + dx);
+                let neighbor = match wrap(position.0, position.1) {
+                    Some(neighbor) if cells.contains(&neighbor) => neighbor,
+                    _ => continue,
+                };
+                match positions.get(&neighbor) {
+                    Some(&other) => wraps_around |= other != position,
+                    None => {
+                        positions.insert(neighbor, position);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+    }
+    Cluster { positions, wraps_around }
+}
+
+/// Move cells so that the top left corner of their bounding box is at row
+/// and column 0, returning them sorted with the offset they were moved by.
+pub fn normalize(cells: &[(i64, i64, Cell)]) -> (Cells, (i64, i64)) {
+    let top = cells.iter().map(|&(row, _, _)| row).min().unwrap_or(0);
+    let left = cells.iter().map(|&(_, col, _)| col).min().unwrap_or(0);
+    let mut normalized: Cells =
+        cells.iter().map(|&(row, col, cell)| (row - top, col - left, cell)).collect();
+    normalized.sort_unstable();
+    (normalized, (top, left))
+}
+
+/// How an object behaves when left alone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Evolution {
+    /// The number of generations until the object first looks the same.
+    pub period: u32,
+    /// How far the object moves down and right every period.
+    pub dy: i64,
+    pub dx: i64,
+    /// The object's cells in each generation of a period, normalized.
+    pub phases: Vec<Cells>,
+}
+
+impl Evolution {
+    /// Whether the object is a still life, an oscillator or a spaceship.
+    pub fn category(&self) -> Category {
+        if self.dy != 0 || self.dx != 0 {
+            Category::Spaceship
+        } else if self.period == 1 {
+            Category::StillLife
+        } else {
+            Category::Oscillator
+        }
+    }
+
+    /// The speed and direction of the object; see `velocity`.
+    pub fn velocity(&self) -> Option<String> {
+        velocity(self.period, self.dy, self.dx)
+    }
+}
+
+/// The speed and direction in c notation of a spaceship that moves `dy`
+/// rows and `dx` columns every `period` generations, such as `c/4 diagonal`
+/// for the glider or `c/2 orthogonal` for the lightweight spaceship, or
+/// `None` for objects that stay in place.
+pub fn velocity(period: u32, dy: i64, dx: i64) -> Option<String> {
+    let (long, short) = (dy.abs().max(dx.abs()), dy.abs().min(dx.abs()));
+    if long == 0 {
+        return None;
+    }
+    let gcd = |mut a: i64, mut b: i64| {
+        while b != 0 {
+            let rest = a % b;
+            a = b;
+            b = rest;
+        }
+        a
+    };
+    let divisor = gcd(gcd(long, short), period as i64);
+    let (long, short, period) = (long / divisor, short / divisor, period as i64 / divisor);
+    let speed = |displacement: i64| match displacement {
+        1 => format!("c/{}", period),
+        displacement => format!("{}c/{}", displacement, period),
+    };
+    Some(if short == 0 {
+        format!("{} orthogonal", speed(long))
+    } else if short == long {
+        format!("{} diagonal", speed(long))
+    } else {
+        format!("({},{})c/{} oblique", long, short, period)
+    })
+}
+
+/// Run `cells` on their own under `rule` for up to `max_period` generations,
+/// until they come back to the same shape, or `None` when they do not or
+/// die out.
+pub fn evolve(rule: &Rule, cells: &[(i64, i64, Cell)], max_period: u32) -> Option<Evolution> {
+    let mut plane = SparsePlane::new();
+    for &(row, col, cell) in cells {
+        plane.set(row, col, cell);
+    }
+    let (start, (top, left)) = normalize(cells);
+    let mut phases = vec![start.clone()];
+    for generation in 1..=max_period {
+        plane = plane.tick(rule);
+        if plane.is_empty() {
+            return None;
+        }
+        let cells: Cells = plane.cells().collect();
+        let (phase, (row, col)) = normalize(&cells);
+        if phase == start {
+            return Some(Evolution {
+                period: generation,
+                dy: row - top,
+                dx: col - left,
+                phases,
+            });
+        }
+        phases.push(phase);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alive(cells: &[(i64, i64)]) -> Cells {
+        cells.iter().map(|&(row, col)| (row, col, Cell::ALIVE)).collect()
+    }
+
+    #[test]
+    fn test_connected() {
+        let cells: HashSet<(i64, i64)> = [(0, 0), (1, 1), (2, 1), (5, 5), (5, 6)].iter().cloned().collect();
+        assert_eq!(connected(&cells, (2, 1)), vec![(0, 0), (1, 1), (2, 1)]);
+        assert_eq!(connected(&cells, (5, 6)), vec![(5, 5), (5, 6)]);
+        assert!(connected(&cells, (3, 3)).is_empty());
+    }
+
+    #[test]
+    fn test_cluster() {
+        let cells: HashSet<(i64, i64)> = [(0, 0), (0, 7), (7, 0), (7, 7), (3, 3)].iter().cloned().collect();
+        let torus = |row: i64, col: i64| Some((row.rem_euclid(8), col.rem_euclid(8)));
+        let corner = cluster(&cells, (7, 7), 1, torus);
+        assert_eq!(corner.positions.len(), 4);
+        assert_eq!(corner.positions[&(0, 0)], (8, 8));
+        assert!(!corner.wraps_around);
+        assert_eq!(cluster(&cells, (7, 7), 1, |row, col| Some((row, col))).positions.len(), 1);
+        assert!(cluster(&cells, (0, 0), 4, torus).wraps_around);
+        assert!(cluster(&cells, (1, 1), 1, torus).positions.is_empty());
+    }
+
+    #[test]
+    fn test_evolve() {
+        let rule = Rule::conway();
+        let block = evolve(&rule, &alive(&[(0, 0), (0, 1), (1, 0), (1, 1)]), 10).unwrap();
+        assert_eq!((block.period, block.dy, block.dx), (1, 0, 0));
+
+        let blinker = evolve(&rule, &alive(&[(0, 0), (0, 1), (0, 2)]), 10).unwrap();
+        assert_eq!((blinker.period, blinker.dy, blinker.dx), (2, 0, 0));
+        assert_eq!(blinker.phases[1], alive(&[(0, 0), (1, 0), (2, 0)]));
+
+        let glider = evolve(&rule, &alive(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]), 10).unwrap();
+        assert_eq!((glider.period, glider.dy, glider.dx), (4, 1, 1));
+        assert_eq!(glider.category(), Category::Spaceship);
+        assert_eq!(glider.velocity().as_deref(), Some("c/4 diagonal"));
+        assert_eq!(blinker.category(), Category::Oscillator);
+        assert_eq!(blinker.velocity(), None);
+        assert_eq!(block.category(), Category::StillLife);
+
+        assert_eq!(evolve(&rule, &alive(&[(0, 0)]), 10), None);
+    }
+
+    #[test]
+    fn test_velocity() {
+        let velocity = |period, dy, dx| velocity(period, dy, dx).unwrap();
+        assert_eq!(velocity(4, 0, -2), "c/2 orthogonal");
+        assert_eq!(velocity(5, 2, 0), "2c/5 orthogonal");
+        assert_eq!(velocity(7, 0, 3), "3c/7 orthogonal");
+        assert_eq!(velocity(12, -2, 2), "c/6 diagonal");
+        assert_eq!(velocity(6, 1, -2), "(2,1)c/6 oblique");
+        assert_eq!(velocity(10, 4, 2), "(2,1)c/5 oblique");
+        assert_eq!(super::velocity(2, 0, 0), None);
+    }
+}
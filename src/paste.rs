@@ -0,0 +1,108 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Cell;
+
+/// The most cells in the box of a pattern pasted in a mode that changes the
+/// cells around the pattern's own.
+pub const MAX_BOX_CELLS: u64 = 1 << 24;
+
+/// How the cells of a pattern pasted into a universe are combined with the
+/// cells already there, over the pattern's bounding box. Cells that are not
+/// dead count as set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PasteMode {
+    /// Replace the cells with the pattern's.
+    #[default]
+    Copy,
+    /// Set the pattern's cells, keeping the others.
+    Or,
+    /// Toggle the cells under the pattern's cells.
+    Xor,
+    /// Keep only the cells under the pattern's cells.
+    And,
+    /// Clear the cells under the pattern's cells.
+    AndNot,
+}
+
+impl PasteMode {
+    /// The cell that `existing` becomes when `pasted` is pasted over it.
+    pub fn combine(self, existing: Cell, pasted: Cell) -> Cell {
+        let set = pasted != Cell::DEAD;
+        match self {
+            PasteMode::Copy => pasted,
+            PasteMode::Or if set => pasted,
+            PasteMode::Xor if set && existing == Cell::DEAD => pasted,
+            PasteMode::Xor if set => Cell::DEAD,
+            PasteMode::And if !set => Cell::DEAD,
+            PasteMode::AndNot if set => Cell::DEAD,
+            _ => existing,
+        }
+    }
+
+    /// Whether the cells in the box that the pattern leaves dead are kept
+    /// as they are, so only the pattern's own cells need pasting.
+    pub fn keeps_dead(self) -> bool {
+        matches!(self, PasteMode::Or | PasteMode::Xor | PasteMode::AndNot)
+    }
+}
+
+impl FromStr for PasteMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<PasteMode, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "copy" => Ok(PasteMode::Copy),
+            "or" => Ok(PasteMode::Or),
+            "xor" => Ok(PasteMode::Xor),
+            "and" => Ok(PasteMode::And),
+            "and_not" => Ok(PasteMode::AndNot),
+            _ => Err(format!(
+                "unknown paste mode \"{}\", expected \"copy\", \"or\", \"xor\", \"and\" or \"and_not\"",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PasteMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PasteMode::Copy => "copy",
+            PasteMode::Or => "or",
+            PasteMode::Xor => "xor",
+            PasteMode::And => "and",
+            PasteMode::AndNot => "and_not",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combine() {
+        let (o, x, y) = (Cell::DEAD, Cell::ALIVE, Cell::new(2));
+        // The results for existing and pasted cells of (dead, dead), (dead,
+        // live), (live, dead), (live, live) and (dying, live).
+        let expected = [
+            (PasteMode::Copy, [o, x, o, x, x]),
+            (PasteMode::Or, [o, x, x, x, x]),
+            (PasteMode::Xor, [o, x, x, o, o]),
+            (PasteMode::And, [o, o, o, x, y]),
+            (PasteMode::AndNot, [o, o, x, o, o]),
+        ];
+        for (mode, results) in expected.iter() {
+            let combined: Vec<Cell> = [(o, o), (o, x), (x, o), (x, x), (y, x)]
+                .iter()
+                .map(|&(existing, pasted)| mode.combine(existing, pasted))
+                .collect();
+            assert_eq!(&combined, results, "{}", mode);
+            assert_eq!(mode.keeps_dead(), results[0] == o && results[2] == x, "{}", mode);
+            assert_eq!(mode.to_string().parse::<PasteMode>().unwrap(), *mode);
+        }
+        assert!("nand".parse::<PasteMode>().is_err());
+    }
+}
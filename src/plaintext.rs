@@ -0,0 +1,100 @@
+use std::fmt;
+
+/// A two-state pattern in the plaintext `.cells` format: `!` starts a comment
+/// line, conventionally `!Name: ...` first, and every other line is a row of
+/// cells, `.` for dead and `O` for live ones.
+///
+/// ```text
+/// !Name: Glider
+/// .O.
+/// ..O
+/// OOO
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plaintext {
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    pub width: u32,
+    pub height: u32,
+    /// The row and column of every live cell, relative to the top left
+    /// corner of the pattern.
+    pub cells: Vec<(u32, u32)>,
+}
+
+impl Plaintext {
+    /// Parse a plaintext pattern. `*` is accepted for live cells as well.
+    pub fn parse(text: &str) -> Result<Plaintext, String> {
+        let mut pattern = Plaintext::default();
+        let mut rows = 0;
+        for line in text.lines().map(str::trim_end) {
+            if let Some(comment) = line.strip_prefix('!') {
+                match comment.strip_prefix("Name:") {
+                    Some(name) if pattern.name.is_none() && rows == 0 => {
+                        pattern.name = Some(name.trim().to_string())
+                    }
+                    _ => pattern.comments.push(comment.trim().to_string()),
+                }
+                continue;
+            }
+            for (col, c) in line.chars().enumerate() {
+                match c {
+                    '.' => {}
+                    'O' | '*' => pattern.cells.push((rows, col as u32)),
+                    _ => {
+                        return Err(format!(
+                            "invalid plaintext pattern: unexpected character '{}' in row {}",
+                            c,
+                            rows + 1
+                        ))
+                    }
+                }
+            }
+            pattern.width = pattern.width.max(line.chars().count() as u32);
+            rows += 1;
+        }
+        // Trailing empty rows are not part of the pattern.
+        pattern.height = pattern.cells.iter().map(|&(row, _)| row + 1).max().unwrap_or(0);
+        Ok(pattern)
+    }
+}
+
+impl fmt::Display for Plaintext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = &self.name {
+            writeln!(f, "!Name: {}", name)?;
+        }
+        for comment in &self.comments {
+            writeln!(f, "!{}", comment)?;
+        }
+        let mut rows = vec![vec!['.'; self.width as usize]; self.height as usize];
+        for &(row, col) in &self.cells {
+            rows[row as usize][col as usize] = 'O';
+        }
+        for row in rows {
+            writeln!(f, "{}", row.into_iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_write() {
+        let text = "!Name: Glider\n!The smallest spaceship.\n.O.\n..O\nOOO\n";
+        let glider = Plaintext::parse(text).unwrap();
+        assert_eq!(glider.name.as_deref(), Some("Glider"));
+        assert_eq!(glider.comments, vec!["The smallest spaceship."]);
+        assert_eq!((glider.width, glider.height), (3, 3));
+        assert_eq!(glider.cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(glider.to_string(), text);
+
+        let ragged = Plaintext::parse("*\n\n..*\n\n").unwrap();
+        assert_eq!(ragged.cells, vec![(0, 0), (2, 2)]);
+        assert_eq!(ragged.to_string(), "O..\n...\n..O\n");
+
+        assert!(Plaintext::parse(".O.\n.X.").is_err());
+    }
+}
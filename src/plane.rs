@@ -0,0 +1,309 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::hashlife::HashLife;
+use crate::rule::Rule;
+use crate::Cell;
+
+/// The width and height of the square tiles a `SparsePlane` is stored in.
+pub const TILE: i64 = 32;
+
+const TILE_CELLS: usize = (TILE * TILE) as usize;
+
+type Tile = Box<[Cell; TILE_CELLS]>;
+
+/// An unbounded plane of cells, stored as a map of square tiles holding at
+/// least one cell that is not dead. Tiles are added as patterns grow into
+/// them and dropped once they die out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SparsePlane {
+    tiles: HashMap<(i64, i64), Tile>,
+}
+
+/// The tile holding a cell and the cell's index within it.
+fn locate(row: i64, col: i64) -> ((i64, i64), usize) {
+    let tile = (row.div_euclid(TILE), col.div_euclid(TILE));
+    let idx = row.rem_euclid(TILE) * TILE + col.rem_euclid(TILE);
+    (tile, idx as usize)
+}
+
+/// The offsets within the tile starting at `tile_start` that fall within the
+/// `len` cells starting at `start`, along one axis.
+fn overlap(tile_start: i64, start: i64, len: i64) -> std::ops::Range<i64> {
+    let first = start.max(tile_start) - tile_start;
+    let last = (start + len).min(tile_start + TILE) - tile_start;
+    first..last.max(first)
+}
+
+impl SparsePlane {
+    pub fn new() -> SparsePlane {
+        SparsePlane::default()
+    }
+
+    pub fn get(&self, row: i64, col: i64) -> Cell {
+        let (tile, idx) = locate(row, col);
+        match self.tiles.get(&tile) {
+            Some(cells) => cells[idx],
+            None => Cell::DEAD,
+        }
+    }
+
+    pub fn set(&mut self, row: i64, col: i64, cell: Cell) {
+        let (tile, idx) = locate(row, col);
+        if cell == Cell::DEAD {
+            if let Some(cells) = self.tiles.get_mut(&tile) {
+                cells[idx] = cell;
+                if cells.iter().all(|&cell| cell == Cell::DEAD) {
+                    self.tiles.remove(&tile);
+                }
+            }
+        } else {
+            self.tiles
+                .entry(tile)
+                .or_insert_with(|| Box::new([Cell::DEAD; TILE_CELLS]))[idx] = cell;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// The position and state of every cell that is not dead, in no
+    /// particular order.
+    pub fn cells(&self) -> impl Iterator<Item = (i64, i64, Cell)> + '_ {
+        self.tiles.iter().flat_map(|(&(tile_row, tile_col), cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|(_, &cell)| cell != Cell::DEAD)
+                .map(move |(idx, &cell)| {
+                    let idx = idx as i64;
+                    (tile_row * TILE + idx / TILE, tile_col * TILE + idx % TILE, cell)
+                })
+        })
+    }
+
+    /// The cells of the `height` by `width` region whose top left cell is at
+    /// `row` and `col`, row by row.
+    pub fn region(&self, row: i64, col: i64, height: u32, width: u32) -> Vec<Cell> {
+        let (height, width) = (height as i64, width as i64);
+        let mut region = vec![Cell::DEAD; (height * width) as usize];
+        for (&(tile_row, tile_col), cells) in &self.tiles {
+            let (tile_top, tile_left) = (tile_row * TILE, tile_col * TILE);
+            for cell_row in overlap(tile_top, row, height) {
+                let region_row = (tile_top + cell_row - row) * width;
+                for cell_col in overlap(tile_left, col, width) {
+                    let region_col = tile_left + cell_col - col;
+                    region[(region_row + region_col) as usize] =
+                        cells[(cell_row * TILE + cell_col) as usize];
+                }
+            }
+        }
+        region
+    }
+
+    /// Which cells are alive in the square window of `TILE + 2 * pad` cells
+    /// per side centered on a tile.
+    fn alive_window(&self, tile_row: i64, tile_col: i64, pad: i64) -> Vec<bool> {
+        let side = TILE + 2 * pad;
+        let top = tile_row * TILE - pad;
+        let left = tile_col * TILE - pad;
+        let mut window = vec![false; (side * side) as usize];
+        let tile_pad = (pad + TILE - 1) / TILE;
+        for neighbor_row in tile_row - tile_pad..=tile_row + tile_pad {
+            for neighbor_col in tile_col - tile_pad..=tile_col + tile_pad {
+                let cells = match self.tiles.get(&(neighbor_row, neighbor_col)) {
+                    Some(cells) => cells,
+                    None => continue,
+                };
+                // The rows and columns of this tile that overlap the window.
+                let (tile_top, tile_left) = (neighbor_row * TILE, neighbor_col * TILE);
+                let rows = overlap(tile_top, top, side);
+                let cols = overlap(tile_left, left, side);
+                for row in rows {
+                    let window_row = (tile_top + row - top) * side;
+                    for col in cols.clone() {
+                        let window_col = tile_left + col - left;
+                        window[(window_row + window_col) as usize] =
+                            cells[(row * TILE + col) as usize].is_alive();
+                    }
+                }
+            }
+        }
+        window
+    }
+
+    /// Advance every cell one generation under `rule`. Only tiles that are
+    /// occupied or within reach of an occupied tile are computed.
+    pub fn tick(&self, rule: &Rule) -> SparsePlane {
+        let pad = rule.larger_than_life().map_or(1, |ltl| ltl.range as i64);
+        let tile_pad = (pad + TILE - 1) / TILE;
+
+        let mut candidates = HashSet::new();
+        for &(tile_row, tile_col) in self.tiles.keys() {
+            for row in tile_row - tile_pad..=tile_row + tile_pad {
+                for col in tile_col - tile_pad..=tile_col + tile_pad {
+                    candidates.insert((row, col));
+                }
+            }
+        }
+
+        let mut next = SparsePlane::new();
+        let dead: Tile = Box::new([Cell::DEAD; TILE_CELLS]);
+        for (tile_row, tile_col) in candidates {
+            let current = self.tiles.get(&(tile_row, tile_col));
+            let window = self.alive_window(tile_row, tile_col, pad);
+            if current.is_none() && !window.contains(&true) {
+                continue;
+            }
+            let current = current.unwrap_or(&dead);
+            let side = TILE + 2 * pad;
+            let alive = |row: i64, col: i64| window[((row + pad) * side + col + pad) as usize];
+
+            let mut cells: Tile = Box::new([Cell::DEAD; TILE_CELLS]);
+            match rule.larger_than_life() {
+                Some(ltl) => {
+                    let counts = ltl.count_neighbors(TILE as u32, TILE as u32, alive);
+                    for (idx, count) in counts.into_iter().enumerate() {
+                        cells[idx] = rule.next_by_count(current[idx], count);
+                    }
+                }
+                None => {
+                    for (idx, next_cell) in cells.iter_mut().enumerate() {
+                        let (row, col) = (idx as i64 / TILE, idx as i64 % TILE);
+                        let mut index = 0;
+                        for bit in 0..9 {
+                            let (dy, dx) = (bit / 3 - 1, bit % 3 - 1);
+                            index |= (alive(row + dy, col + dx) as usize) << bit;
+                        }
+                        *next_cell = rule.next(current[idx], index);
+                    }
+                }
+            }
+            if cells.iter().any(|&cell| cell != Cell::DEAD) {
+                next.tiles.insert((tile_row, tile_col), cells);
+            }
+        }
+        next
+    }
+}
+
+/// The storage of an unbounded universe, which also decides the algorithm
+/// it is advanced with.
+#[derive(Clone, Debug)]
+pub enum Plane {
+    Sparse(SparsePlane),
+    HashLife(Box<HashLife>),
+}
+
+impl Plane {
+    pub fn get(&self, row: i64, col: i64) -> Cell {
+        match self {
+            Plane::Sparse(plane) => plane.get(row, col),
+            Plane::HashLife(hashlife) => hashlife.get(row, col),
+        }
+    }
+
+    pub fn set(&mut self, row: i64, col: i64, cell: Cell) {
+        match self {
+            Plane::Sparse(plane) => plane.set(row, col, cell),
+            Plane::HashLife(hashlife) => hashlife.set(row, col, cell),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            Plane::Sparse(plane) => plane.clear(),
+            Plane::HashLife(hashlife) => hashlife.clear(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Plane::Sparse(plane) => plane.is_empty(),
+            Plane::HashLife(hashlife) => hashlife.is_empty(),
+        }
+    }
+
+    /// The number of live cells.
+    pub fn population(&self) -> u64 {
+        match self {
+            Plane::Sparse(plane) => plane.cells().filter(|&(_, _, cell)| cell.is_alive()).count() as u64,
+            Plane::HashLife(hashlife) => hashlife.population(),
+        }
+    }
+
+    /// The position and state of every cell that is not dead, in no
+    /// particular order.
+    pub fn cells(&self) -> Vec<(i64, i64, Cell)> {
+        match self {
+            Plane::Sparse(plane) => plane.cells().collect(),
+            Plane::HashLife(hashlife) => hashlife.cells(),
+        }
+    }
+
+    pub fn region(&self, row: i64, col: i64, height: u32, width: u32) -> Vec<Cell> {
+        match self {
+            Plane::Sparse(plane) => plane.region(row, col, height, width),
+            Plane::HashLife(hashlife) => hashlife.region(row, col, height, width),
+        }
+    }
+
+    /// Advance every cell `generations` generations under `rule`.
+    pub fn step(&mut self, rule: &Rule, generations: u64) {
+        match self {
+            Plane::Sparse(plane) => {
+                for _ in 0..generations {
+                    *plane = plane.tick(rule);
+                }
+            }
+            Plane::HashLife(hashlife) => {
+                hashlife.set_rule(rule);
+                hashlife.step(generations);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_region() {
+        let mut plane = SparsePlane::new();
+        plane.set(-1, -1, Cell::ALIVE);
+        plane.set(40, 70, Cell::new(2));
+        assert_eq!(plane.get(-1, -1), Cell::ALIVE);
+        assert_eq!(plane.get(40, 70), Cell::new(2));
+        assert_eq!(plane.cells().count(), 2);
+
+        let region = plane.region(-2, -2, 3, 3);
+        assert_eq!(region[4], Cell::ALIVE);
+        assert_eq!(region.iter().filter(|&&cell| cell != Cell::DEAD).count(), 1);
+
+        plane.set(-1, -1, Cell::DEAD);
+        plane.set(40, 70, Cell::DEAD);
+        assert!(plane.is_empty());
+    }
+
+    #[test]
+    fn test_glider_crosses_tiles() {
+        let mut plane = SparsePlane::new();
+        for &(row, col) in &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            plane.set(row, col, Cell::ALIVE);
+        }
+        let rule = Rule::conway();
+        // A glider moves one cell diagonally every four generations, so after
+        // 4 * 100 generations it is 100 cells away, three tiles over.
+        for _ in 0..400 {
+            plane = plane.tick(&rule);
+        }
+        let mut cells: Vec<(i64, i64)> = plane.cells().map(|(row, col, _)| (row, col)).collect();
+        cells.sort();
+        assert_eq!(cells, vec![(100, 101), (101, 102), (102, 100), (102, 101), (102, 102)]);
+    }
+}
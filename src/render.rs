@@ -1,4 +1,3 @@
-use js_sys::WebAssembly;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{WebGlProgram, WebGlRenderingContext, WebGlShader};
@@ -29,12 +28,27 @@ pub fn start(width: u32, height: u32) -> Result<(Option<WebGlProgram>, Option<We
         }
     "#,
     )?;
+    let cell_vert_shader = compile_shader(
+        &context,
+        WebGlRenderingContext::VERTEX_SHADER,
+        r#"
+        attribute vec4 position;
+        attribute float shade;
+        varying float v_shade;
+        void main() {
+            gl_Position = position;
+            v_shade = shade;
+        }
+    "#,
+    )?;
     let cell_frag_shader = compile_shader(
         &context,
         WebGlRenderingContext::FRAGMENT_SHADER,
         r#"
+        precision mediump float;
+        varying float v_shade;
         void main() {
-            gl_FragColor = vec4(0.7, 0.8, 0.71, 1.0);
+            gl_FragColor = vec4(mix(vec3(0.3, 0.3, 0.3), vec3(0.7, 0.8, 0.71), v_shade), 1.0);
         }
     "#,
     )?;
@@ -49,12 +63,14 @@ pub fn start(width: u32, height: u32) -> Result<(Option<WebGlProgram>, Option<We
     "#,
     )?;
 
-    let cell_program = link_program(&context, &vert_shader, &cell_frag_shader)?;
+    let cell_program = link_program(&context, &cell_vert_shader, &cell_frag_shader)?;
     let grid_program = link_program(&context, &vert_shader, &grid_frag_shader)?;
 
-    return Ok((Some(cell_program), Some(grid_program)));
+    Ok((Some(cell_program), Some(grid_program)))
 }
 
+/// Draw the cells and the grid. Each cell vertex is four floats: the x, y and
+/// z position followed by the shade to draw the cell with.
 pub fn render(
     cell_program: &WebGlProgram,
     vertices: Vec<f32>,
@@ -83,8 +99,12 @@ pub fn render(
             WebGlRenderingContext::STATIC_DRAW,
         );
     }
-    context.vertex_attrib_pointer_with_i32(0, 3, WebGlRenderingContext::FLOAT, false, 0, 0);
-    context.enable_vertex_attrib_array(0);
+    let position = context.get_attrib_location(cell_program, "position") as u32;
+    let shade = context.get_attrib_location(cell_program, "shade") as u32;
+    context.vertex_attrib_pointer_with_i32(position, 3, WebGlRenderingContext::FLOAT, false, 16, 0);
+    context.enable_vertex_attrib_array(position);
+    context.vertex_attrib_pointer_with_i32(shade, 1, WebGlRenderingContext::FLOAT, false, 16, 12);
+    context.enable_vertex_attrib_array(shade);
 
     context.clear_color(0.3, 0.3, 0.3, 1.0);
     context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
@@ -92,8 +112,9 @@ pub fn render(
     context.draw_arrays(
         WebGlRenderingContext::TRIANGLES,
         0,
-        (vertices.len() / 3) as i32,
+        (vertices.len() / 4) as i32,
     );
+    context.disable_vertex_attrib_array(shade);
 
     context.use_program(Some(grid_program));
     let buffer = context.create_buffer().ok_or("failed to create buffer")?;
@@ -117,8 +138,9 @@ pub fn render(
         );
     }
 
-    context.vertex_attrib_pointer_with_i32(0, 3, WebGlRenderingContext::FLOAT, false, 0, 0);
-    context.enable_vertex_attrib_array(0);
+    let position = context.get_attrib_location(grid_program, "position") as u32;
+    context.vertex_attrib_pointer_with_i32(position, 3, WebGlRenderingContext::FLOAT, false, 0, 0);
+    context.enable_vertex_attrib_array(position);
 
     context.draw_arrays(
         WebGlRenderingContext::LINES,
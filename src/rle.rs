@@ -0,0 +1,334 @@
+use std::fmt;
+
+use crate::Cell;
+
+/// The longest line written by `Rle`'s `Display` implementation.
+const LINE_LENGTH: usize = 70;
+
+/// A pattern in run length encoded form, the format most patterns are shared
+/// in:
+///
+/// ```text
+/// #N Glider
+/// #O Richard K. Guy
+/// #C The smallest, most common, and first discovered spaceship.
+/// x = 3, y = 3, rule = B3/S23
+/// bob$2bo$3o!
+/// ```
+///
+/// Two-state patterns use `b` for dead and `o` for live cells. Patterns with
+/// more states use `.` for dead cells and `A` to `X`, then `pA` to `pX`, `qA`
+/// and so on up to `yO` for states 1 to 255. Runs of the same state are
+/// prefixed by their length, and `$` ends a row.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rle {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Whether cells are written with multi-state letters.
+    pub multi_state: bool,
+    /// The row, column and state of every cell that is not dead, relative to
+    /// the top left corner of the pattern.
+    pub cells: Vec<(u32, u32, Cell)>,
+}
+
+/// The most cells that are not dead a pattern may have, whatever size its
+/// header gives.
+const MAX_CELLS: usize = 1 << 24;
+
+/// The letters for a cell state.
+fn state_letters(state: u8, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => String::from("b"),
+        (_, false) => String::from("o"),
+        (0, true) => String::from("."),
+        (state, true) if state <= 24 => char::from(b'A' + state - 1).to_string(),
+        (state, true) => {
+            let prefix = char::from(b'p' + (state - 25) / 24);
+            let letter = char::from(b'A' + (state - 25) % 24);
+            format!("{}{}", prefix, letter)
+        }
+    }
+}
+
+fn parse_header(line: &str, rle: &mut Rle) -> Result<(), String> {
+    // Rules may hold commas, as in `rule = B3/S23:T64,64`, so pieces with
+    // no `=` belong to the field before them.
+    let mut fields: Vec<String> = Vec::new();
+    for piece in line.split(',') {
+        match fields.last_mut() {
+            Some(field) if !piece.contains('=') => {
+                field.push(',');
+                field.push_str(piece);
+            }
+            _ => fields.push(piece.to_string()),
+        }
+    }
+    for field in &fields {
+        let (key, value) = match field.find('=') {
+            Some(equals) => (field[..equals].trim(), field[equals + 1..].trim()),
+            None => {
+                return Err(format!(
+                    "invalid RLE header \"{}\": expected \"key = value\" fields",
+                    line.trim()
+                ))
+            }
+        };
+        let size = || {
+            value.parse::<u32>().map_err(|_| {
+                format!(
+                    "invalid RLE header \"{}\": expected a size for '{}', found \"{}\"",
+                    line.trim(),
+                    key,
+                    value
+                )
+            })
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "x" => rle.width = size()?,
+            "y" => rle.height = size()?,
+            "rule" => rle.rule = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+impl Rle {
+    /// Parse a run length encoded pattern. The header line is optional; the
+    /// size is taken from the cells when it is left out, and cells beyond
+    /// the size in a header are an error.
+    pub fn parse(text: &str) -> Result<Rle, String> {
+        let mut rle = Rle::default();
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let mut body = String::new();
+        let mut header = false;
+        for line in &mut lines {
+            if let Some(comment) = line.strip_prefix('#') {
+                let mut chars = comment.chars();
+                let kind = chars.next();
+                let value = chars.as_str().trim().to_string();
+                match kind {
+                    Some('N') => rle.name = Some(value),
+                    Some('O') => rle.author = Some(value),
+                    Some('C') | Some('c') => rle.comments.push(value),
+                    _ => {}
+                }
+            } else if line.starts_with(['x', 'X']) && line.contains('=') {
+                parse_header(line, &mut rle)?;
+                header = true;
+            } else {
+                body.push_str(line);
+                break;
+            }
+        }
+        for line in lines {
+            body.push_str(line);
+        }
+
+        // Runs may not go past the size in the header, and the cells they
+        // set not past `MAX_CELLS`.
+        let too_large = || String::from("invalid RLE: the pattern is larger than its header says");
+        let (max_width, max_height) = if header { (rle.width, rle.height) } else { (u32::MAX, u32::MAX) };
+        let (mut row, mut col) = (0u32, 0u32);
+        let (mut width, mut height) = (0, 0);
+        let mut count: Option<u32> = None;
+        let mut prefix: Option<u8> = None;
+        let mut ended = false;
+        for c in body.chars() {
+            if ended {
+                break;
+            }
+            let state = match c {
+                '0'..='9' if prefix.is_none() => {
+                    let digit = c as u32 - '0' as u32;
+                    count = Some(
+                        count
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|count| count.checked_add(digit))
+                            .ok_or_else(|| String::from("invalid RLE: run count is too large"))?,
+                    );
+                    continue;
+                }
+                c if c.is_whitespace() => continue,
+                '!' => {
+                    ended = true;
+                    continue;
+                }
+                '$' => {
+                    row = row
+                        .checked_add(count.take().unwrap_or(1))
+                        .ok_or_else(|| String::from("invalid RLE: too many rows"))?;
+                    col = 0;
+                    continue;
+                }
+                'p'..='y' if prefix.is_none() => {
+                    prefix = Some(c as u8 - b'p' + 1);
+                    continue;
+                }
+                'A'..='X' => {
+                    let state = prefix.take().unwrap_or(0) as u32 * 24 + (c as u32 - 'A' as u32) + 1;
+                    if state > 255 {
+                        return Err(format!("invalid RLE: state {} is above 255", state));
+                    }
+                    rle.multi_state = true;
+                    state as u8
+                }
+                'b' | '.' if prefix.is_none() => 0,
+                'o' if prefix.is_none() => 1,
+                _ => return Err(format!("invalid RLE: unexpected character '{}'", c)),
+            };
+            let run = count.take().unwrap_or(1);
+            let end = col
+                .checked_add(run)
+                .ok_or_else(|| String::from("invalid RLE: too many columns"))?;
+            if state != 0 {
+                if end > max_width || row >= max_height {
+                    return Err(too_large());
+                }
+                if rle.cells.len() + run as usize > MAX_CELLS {
+                    return Err(format!("invalid RLE: more than {} cells", MAX_CELLS));
+                }
+                rle.cells.extend((col..end).map(|col| (row, col, Cell::new(state))));
+                width = width.max(end);
+                height = height.max(row + 1);
+            }
+            col = end;
+        }
+        if prefix.is_some() {
+            return Err(String::from("invalid RLE: state prefix without a letter"));
+        }
+        rle.width = rle.width.max(width);
+        rle.height = rle.height.max(height);
+        Ok(rle)
+    }
+}
+
+impl fmt::Display for Rle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = &self.name {
+            writeln!(f, "#N {}", name)?;
+        }
+        if let Some(author) = &self.author {
+            writeln!(f, "#O {}", author)?;
+        }
+        for comment in &self.comments {
+            writeln!(f, "#C {}", comment)?;
+        }
+        write!(f, "x = {}, y = {}", self.width, self.height)?;
+        match &self.rule {
+            Some(rule) => writeln!(f, ", rule = {}", rule)?,
+            None => writeln!(f)?,
+        }
+
+        let mut cells = self.cells.clone();
+        cells.sort_by_key(|&(row, col, _)| (row, col));
+
+        // Runs as (length, letters), with rows ended by `$` runs. Dead runs
+        // at the end of a row and empty rows at the end are left out.
+        let mut runs: Vec<(u32, String)> = Vec::new();
+        let push = |runs: &mut Vec<(u32, String)>, length: u32, letters: String| {
+            match runs.last_mut() {
+                Some((last_length, last_letters)) if *last_letters == letters => {
+                    *last_length += length
+                }
+                _ => runs.push((length, letters)),
+            }
+        };
+        let (mut row, mut col) = (0, 0);
+        for (cell_row, cell_col, cell) in cells {
+            if cell_row > row {
+                push(&mut runs, cell_row - row, String::from("$"));
+                row = cell_row;
+                col = 0;
+            }
+            if cell_col > col {
+                push(&mut runs, cell_col - col, state_letters(0, self.multi_state));
+            }
+            push(&mut runs, 1, state_letters(cell.state(), self.multi_state));
+            col = cell_col + 1;
+        }
+        push(&mut runs, 1, String::from("!"));
+
+        let mut line = String::new();
+        for (length, letters) in runs {
+            let run = match length {
+                1 => letters,
+                length => format!("{}{}", length, letters),
+            };
+            if line.len() + run.len() > LINE_LENGTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            line.push_str(&run);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let glider = Rle::parse(
+            "#N Glider\n#O Richard K. Guy\n#C The smallest spaceship.\n#C Found in 1969.\n\
+             x = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n",
+        )
+        .unwrap();
+        assert_eq!(glider.name.as_deref(), Some("Glider"));
+        assert_eq!(glider.author.as_deref(), Some("Richard K. Guy"));
+        assert_eq!(glider.comments.len(), 2);
+        assert_eq!(glider.rule.as_deref(), Some("B3/S23"));
+        assert_eq!((glider.width, glider.height), (3, 3));
+        let cells: Vec<(u32, u32)> = glider.cells.iter().map(|&(row, col, _)| (row, col)).collect();
+        assert_eq!(cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+
+        let multi = Rle::parse("x = 4, y = 4, rule = /2/3\n.AB$\n2$pAyO!").unwrap();
+        assert!(multi.multi_state);
+        assert_eq!(
+            multi.cells,
+            vec![(0, 1, Cell::new(1)), (0, 2, Cell::new(2)), (3, 0, Cell::new(25)), (3, 1, Cell::new(255))]
+        );
+        assert_eq!(multi.height, 4);
+
+        assert!(Rle::parse("x = 3, y = 3\nbo?!").is_err());
+        // Runs past the header's size, or past what a row can hold.
+        assert!(Rle::parse("x = 4, y = 3, rule = /2/3\n.AB$\n2$pAyO!").is_err());
+        assert!(Rle::parse("x = 3, y = 1\n4o!").is_err());
+        assert!(Rle::parse("x = 3, y = 3\n4000000000o!").is_err());
+        assert!(Rle::parse("x = 4000000000, y = 1\n4000000000o!").is_err());
+        assert!(Rle::parse("4000000000o!").is_err());
+        assert!(Rle::parse("4000000000b4000000000o!").is_err());
+        assert!(Rle::parse("4000000000$4000000000$o!").is_err());
+        assert!(Rle::parse("x = a, y = 3\nbo!").is_err());
+        assert!(Rle::parse("yP!").is_err());
+        assert!(Rle::parse("3p!").is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let text = "#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+        assert_eq!(Rle::parse(text).unwrap().to_string(), text);
+
+        let multi = "x = 26, y = 1, rule = /2/30\nA24.pA!\n";
+        assert_eq!(Rle::parse(multi).unwrap().to_string(), multi);
+
+        // Long rows wrap at 70 characters without splitting runs.
+        let row: Vec<(u32, u32, Cell)> = (0..100).map(|col| (0, col * 2, Cell::ALIVE)).collect();
+        let rle = Rle {
+            width: 199,
+            height: 1,
+            cells: row,
+            ..Rle::default()
+        };
+        let text = rle.to_string();
+        assert!(text.lines().all(|line| line.len() <= LINE_LENGTH));
+        assert_eq!(Rle::parse(&text).unwrap().cells, rle.cells);
+    }
+}
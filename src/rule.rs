@@ -0,0 +1,636 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ltl::LargerThanLife;
+use crate::Cell;
+
+/// The Hensel notation letters that distinguish neighbor configurations with
+/// the same number of live neighbors, indexed by neighbor count.
+const LETTERS: [&str; 9] = [
+    "",
+    "ce",
+    "cekain",
+    "cekainyqjr",
+    "cekainyqjrtwz",
+    "cekainyqjr",
+    "cekain",
+    "ce",
+    "",
+];
+
+// Bits of a neighborhood configuration index, laid out row by row.
+const NW: usize = 1 << 0;
+const N: usize = 1 << 1;
+const NE: usize = 1 << 2;
+const W: usize = 1 << 3;
+const CENTER: usize = 1 << 4;
+const E: usize = 1 << 5;
+const SW: usize = 1 << 6;
+const S: usize = 1 << 7;
+const SE: usize = 1 << 8;
+
+const NEIGHBORS: usize = NW | N | NE | W | E | SW | S | SE;
+
+/// One representative configuration for each letter of `LETTERS`, for one to
+/// four neighbors. Configurations with five to seven neighbors are the
+/// complements of those with three to one.
+const REPRESENTATIVES: [&[usize]; 5] = [
+    &[],
+    &[NW, N],
+    &[NW | NE, N | W, N | SE, NW | N, N | S, NW | SE],
+    &[
+        NW | NE | SW,
+        N | W | E,
+        N | W | SE,
+        NW | N | W,
+        NW | W | SW,
+        NW | SW | N,
+        NW | NE | S,
+        NW | N | SE,
+        NW | N | E,
+        NW | N | S,
+    ],
+    &[
+        NW | NE | SW | SE,
+        N | W | E | S,
+        N | NE | W | SE,
+        NW | N | W | SW,
+        NW | N | SW | S,
+        NW | N | NE | SE,
+        NW | NE | SE | W,
+        NW | N | W | SE,
+        N | E | S | SW,
+        N | E | S | SE,
+        NW | N | NE | S,
+        NW | W | S | SE,
+        NW | N | S | SE,
+    ],
+];
+
+/// Apply one of the eight symmetries of the square to a configuration index.
+fn transform(index: usize, symmetry: usize) -> usize {
+    let mut result = 0;
+    for bit in (0..9).filter(|bit| index & (1 << bit) != 0) {
+        let (row, col) = (bit / 3, bit % 3);
+        let (row, col) = if symmetry & 4 != 0 { (col, row) } else { (row, col) };
+        let row = if symmetry & 2 != 0 { 2 - row } else { row };
+        let col = if symmetry & 1 != 0 { 2 - col } else { col };
+        result |= 1 << (row * 3 + col);
+    }
+    result
+}
+
+/// Classify the neighbors of a configuration index by their live neighbor
+/// count and the position of their Hensel letter within `LETTERS`.
+fn classify(index: usize) -> (usize, usize) {
+    let neighbors = index & NEIGHBORS;
+    let count = neighbors.count_ones() as usize;
+    let (reference, complement) = if count > 4 {
+        (8 - count, NEIGHBORS & !neighbors)
+    } else {
+        (count, neighbors)
+    };
+    let letter = REPRESENTATIVES[reference]
+        .iter()
+        .position(|&representative| {
+            (0..8).any(|symmetry| transform(representative, symmetry) == complement)
+        })
+        .unwrap_or(0);
+    (count, letter)
+}
+
+/// Every letter for `count` neighbors, as a bitmask. Counts without letters
+/// use a single bit.
+fn all_letters(count: usize) -> u16 {
+    (1 << LETTERS[count].len().max(1)) - 1
+}
+
+/// A cellular automaton rule.
+///
+/// Life-like rules are described by the neighbor configurations that cause a
+/// dead cell to be born and a live cell to survive. Totalistic rules
+/// (`B36/S23`) and isotropic non-totalistic rules in Hensel notation
+/// (`B2-a/S12`) are both supported. Larger than Life rules
+/// (`R5,C0,M1,S34..58,B34..45,NM`) instead count live cells over an extended
+/// neighborhood.
+///
+/// Rules with more than two states are Generations rules: a live cell that
+/// does not survive enters the first dying state instead of dying, and dying
+/// cells advance one state per generation until they are dead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    states: u8,
+    kind: Kind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    Isotropic {
+        birth: [u16; 9],
+        survival: [u16; 9],
+        table: Box<[Cell; 512]>,
+    },
+    LargerThanLife(LargerThanLife),
+}
+
+impl Rule {
+    /// Create a rule from bitmasks of the Hensel letters allowed for each
+    /// neighbor count and the number of cell states.
+    pub fn new(birth: [u16; 9], survival: [u16; 9], states: u8) -> Rule {
+        let mut table = [Cell::DEAD; 512];
+        for (index, next) in table.iter_mut().enumerate() {
+            let (count, letter) = classify(index);
+            let letters = if index & CENTER != 0 {
+                survival[count]
+            } else {
+                birth[count]
+            };
+            if letters & (1 << letter) != 0 {
+                *next = Cell::ALIVE;
+            }
+        }
+        Rule {
+            states,
+            kind: Kind::Isotropic {
+                birth,
+                survival,
+                table: Box::new(table),
+            },
+        }
+    }
+
+    /// Conway's Game of Life, B3/S23.
+    pub fn conway() -> Rule {
+        "B3/S23".parse().unwrap()
+    }
+
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// The Larger than Life parameters of the rule, or `None` for rules that
+    /// only look at the eight nearest neighbors.
+    pub fn larger_than_life(&self) -> Option<&LargerThanLife> {
+        match &self.kind {
+            Kind::LargerThanLife(ltl) => Some(ltl),
+            Kind::Isotropic { .. } => None,
+        }
+    }
+
+    /// The neighbor counts causing births and survivals as bitmasks, bit `n`
+    /// being set for `n` live neighbors, or `None` when the rule depends on
+    /// more than the number of live neighbors.
+    pub fn totalistic(&self) -> Option<(u16, u16)> {
+        let (birth, survival) = match &self.kind {
+            Kind::Isotropic {
+                birth, survival, ..
+            } => (birth, survival),
+            Kind::LargerThanLife(_) => return None,
+        };
+        let mask = |letters: &[u16; 9]| {
+            let mut mask = 0;
+            for (count, &letters) in letters.iter().enumerate() {
+                if letters == all_letters(count) {
+                    mask |= 1 << count;
+                } else if letters != 0 {
+                    return None;
+                }
+            }
+            Some(mask)
+        };
+        Some((mask(birth)?, mask(survival)?))
+    }
+
+    /// Whether dead cells with no live neighbors are born, which would fill
+    /// an unbounded plane.
+    pub fn births_from_nothing(&self) -> bool {
+        match &self.kind {
+            Kind::Isotropic { table, .. } => table[0] == Cell::ALIVE,
+            Kind::LargerThanLife(ltl) => ltl.lives(false, 0),
+        }
+    }
+
+    /// Age `cell` given whether the rule makes it alive next generation.
+    fn age(&self, cell: Cell, lives: bool) -> Cell {
+        match cell.state() {
+            0 | 1 if lives => Cell::ALIVE,
+            0 => Cell::DEAD,
+            state if state + 1 < self.states => Cell::new(state + 1),
+            _ => Cell::DEAD,
+        }
+    }
+
+    /// Look up the next state of `cell` given its neighborhood configuration
+    /// index: bit `row * 3 + col` is set when the cell at that position of
+    /// the 3x3 block centered on it is alive.
+    pub fn next(&self, cell: Cell, index: usize) -> Cell {
+        match &self.kind {
+            Kind::Isotropic { table, .. } => self.age(cell, table[index] == Cell::ALIVE),
+            Kind::LargerThanLife(_) => self.next_by_count(cell, (index & NEIGHBORS).count_ones()),
+        }
+    }
+
+    /// The next state of `cell` given the number of live cells in its
+    /// neighborhood. Isotropic rules treat this as a totalistic count.
+    pub fn next_by_count(&self, cell: Cell, count: u32) -> Cell {
+        let lives = match &self.kind {
+            Kind::Isotropic {
+                birth, survival, ..
+            } => {
+                let letters = if cell.is_alive() { survival } else { birth };
+                letters[count as usize] == all_letters(count as usize)
+            }
+            Kind::LargerThanLife(ltl) => ltl.lives(cell.is_alive(), count),
+        };
+        self.age(cell, lives)
+    }
+
+    /// How brightly to draw a cell, from 1.0 for live cells down towards 0.0
+    /// for the oldest dying state.
+    pub fn shade(&self, cell: Cell) -> f32 {
+        match cell.state() {
+            0 => 0.0,
+            state => 1.0 - (state - 1) as f32 / (self.states - 1) as f32,
+        }
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::conway()
+    }
+}
+
+fn parse_counts(rule: &str, counts: &str) -> Result<[u16; 9], String> {
+    let mut set = [0; 9];
+    let mut chars = counts.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = match c.to_digit(10) {
+            Some(count) if count <= 8 => count as usize,
+            Some(count) => {
+                return Err(format!(
+                    "invalid rule \"{}\": neighbor count {} is out of range (0-8)",
+                    rule, count
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "invalid rule \"{}\": unexpected character '{}'",
+                    rule, c
+                ))
+            }
+        };
+
+        let negate = chars.peek() == Some(&'-');
+        if negate {
+            chars.next();
+        }
+        let mut letters = 0;
+        while let Some(&letter) = chars.peek() {
+            if !letter.is_ascii_alphabetic() {
+                break;
+            }
+            chars.next();
+            match LETTERS[count].find(letter) {
+                Some(position) => letters |= 1 << position,
+                None => {
+                    return Err(format!(
+                        "invalid rule \"{}\": '{}' is not a valid letter for {} neighbors",
+                        rule, letter, count
+                    ))
+                }
+            }
+        }
+        set[count] |= match (negate, letters) {
+            (true, 0) => {
+                return Err(format!(
+                    "invalid rule \"{}\": expected letters after '-' for {} neighbors",
+                    rule, count
+                ))
+            }
+            (true, letters) => all_letters(count) & !letters,
+            (false, 0) => all_letters(count),
+            (false, letters) => letters,
+        };
+    }
+    Ok(set)
+}
+
+fn parse_states(rule: &str, states: &str) -> Result<u8, String> {
+    match states.parse::<u8>() {
+        Ok(states) if states >= 2 => Ok(states),
+        _ => Err(format!(
+            "invalid rule \"{}\": number of states \"{}\" must be between 2 and 255",
+            rule, states
+        )),
+    }
+}
+
+fn write_counts(f: &mut fmt::Formatter, set: &[u16; 9]) -> fmt::Result {
+    for (count, &letters) in set.iter().enumerate() {
+        if letters == 0 {
+            continue;
+        }
+        write!(f, "{}", count)?;
+        let all = all_letters(count);
+        if letters == all {
+            continue;
+        }
+        let present = letters.count_ones();
+        let (prefix, shown) = if present * 2 > all.count_ones() {
+            ("-", all & !letters)
+        } else {
+            ("", letters)
+        };
+        write!(f, "{}", prefix)?;
+        for (position, letter) in LETTERS[count].chars().enumerate() {
+            if shown & (1 << position) != 0 {
+                write!(f, "{}", letter)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Split `b3s23` style text into its `b3` and `s23` sections.
+fn split_sections(text: &str) -> Vec<&str> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices().skip(1) {
+        if c == 'b' || c == 's' {
+            sections.push(&text[start..i]);
+            start = i;
+        }
+    }
+    if !text.is_empty() {
+        sections.push(&text[start..]);
+    }
+    sections
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    /// Parse a rulestring in either B/S notation (`B36/S23`, `b36s23`,
+    /// `S23/B36`, `B2-a/S12`) or the legacy S/B notation (`23/36`). Either
+    /// may be followed by a number of states to make a Generations rule
+    /// (`B2/S/C3`, `345/2/4`). Larger than Life rules use Golly's
+    /// `R5,C0,M1,S34..58,B34..45,NM` notation.
+    fn from_str(rule: &str) -> Result<Rule, String> {
+        let trimmed = rule.trim();
+        if trimmed.is_empty() {
+            return Err(String::from("invalid rule \"\": rule is empty"));
+        }
+
+        let lower = trimmed.to_ascii_lowercase();
+        if lower.starts_with('r') {
+            let (ltl, states) = LargerThanLife::parse(trimmed)?;
+            return Ok(Rule {
+                states,
+                kind: Kind::LargerThanLife(ltl),
+            });
+        }
+        let (birth, survival, states) = if lower.contains('b') || lower.contains('s') {
+            let mut birth = None;
+            let mut survival = None;
+            let mut states = None;
+            for section in lower.split('/').flat_map(split_sections) {
+                if let Some(count) = section.strip_prefix(|c| c == 'c' || c == 'g') {
+                    if states.is_some() {
+                        return Err(format!(
+                            "invalid rule \"{}\": 'C' appears more than once",
+                            trimmed
+                        ));
+                    }
+                    states = Some(parse_states(trimmed, count)?);
+                    continue;
+                }
+                let mut chars = section.chars();
+                let prefix = chars.next().unwrap();
+                let counts = chars.as_str();
+                let slot = match prefix {
+                    'b' => &mut birth,
+                    's' => &mut survival,
+                    _ => {
+                        return Err(format!(
+                            "invalid rule \"{}\": expected 'B' or 'S' before \"{}\"",
+                            trimmed, section
+                        ))
+                    }
+                };
+                if slot.is_some() {
+                    return Err(format!(
+                        "invalid rule \"{}\": '{}' appears more than once",
+                        trimmed,
+                        prefix.to_ascii_uppercase()
+                    ));
+                }
+                *slot = Some(parse_counts(trimmed, counts)?);
+            }
+            match (birth, survival) {
+                (Some(birth), Some(survival)) => (birth, survival, states.unwrap_or(2)),
+                _ => {
+                    return Err(format!(
+                        "invalid rule \"{}\": both 'B' and 'S' sections are required",
+                        trimmed
+                    ))
+                }
+            }
+        } else {
+            let mut parts = lower.split('/');
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(survival), Some(birth), states, None) => (
+                    parse_counts(trimmed, birth)?,
+                    parse_counts(trimmed, survival)?,
+                    match states {
+                        Some(states) => parse_states(trimmed, states)?,
+                        None => 2,
+                    },
+                ),
+                _ => {
+                    return Err(format!(
+                        "invalid rule \"{}\": expected \"B<counts>/S<counts>\" or \"<survival>/<birth>\"",
+                        trimmed
+                    ))
+                }
+            }
+        };
+
+        Ok(Rule::new(birth, survival, states))
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (birth, survival) = match &self.kind {
+            Kind::Isotropic {
+                birth, survival, ..
+            } => (birth, survival),
+            Kind::LargerThanLife(ltl) => return ltl.write(f, self.states),
+        };
+        write!(f, "B")?;
+        write_counts(f, birth)?;
+        write!(f, "/S")?;
+        write_counts(f, survival)?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notations() {
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert_eq!(highlife.to_string(), "B36/S23");
+        assert_eq!("b36s23".parse::<Rule>().unwrap(), highlife);
+        assert_eq!("S23/B36".parse::<Rule>().unwrap(), highlife);
+        assert_eq!("23/36".parse::<Rule>().unwrap(), highlife);
+        assert_eq!("/2".parse::<Rule>().unwrap().to_string(), "B2/S");
+        assert_eq!("B2/S".parse::<Rule>().unwrap().to_string(), "B2/S");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("".parse::<Rule>().is_err());
+        assert!("B39/S23".parse::<Rule>().is_err());
+        assert!("B3/S2x".parse::<Rule>().is_err());
+        assert!("B3".parse::<Rule>().is_err());
+        assert!("B3/B4/S23".parse::<Rule>().is_err());
+        assert!("23/3/1".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let life = Rule::conway();
+        assert_eq!(life.next(Cell::DEAD, NW | N | NE), Cell::ALIVE);
+        assert_eq!(life.next(Cell::DEAD, NW | SE), Cell::DEAD);
+        assert_eq!(life.next(Cell::ALIVE, CENTER | W | E), Cell::ALIVE);
+        assert_eq!(life.next(Cell::ALIVE, CENTER | N | S | W | E), Cell::DEAD);
+
+        assert_eq!(life.totalistic(), Some((1 << 3, 1 << 2 | 1 << 3)));
+        assert_eq!("B2-a/S12".parse::<Rule>().unwrap().totalistic(), None);
+    }
+
+    #[test]
+    fn test_letters_partition_configurations() {
+        for count in 0..9 {
+            let mut members = vec![0; LETTERS[count].len().max(1)];
+            for neighbors in (0..512).filter(|index| index & CENTER == 0) {
+                if neighbors.count_ones() as usize != count {
+                    continue;
+                }
+                let target = if count > 4 {
+                    NEIGHBORS & !neighbors
+                } else {
+                    neighbors
+                };
+                let matches = REPRESENTATIVES[count.min(8 - count)]
+                    .iter()
+                    .filter(|&&representative| {
+                        (0..8).any(|symmetry| transform(representative, symmetry) == target)
+                    })
+                    .count();
+                if count > 0 && count < 8 {
+                    assert_eq!(matches, 1, "{:09b} matches {} letters", neighbors, matches);
+                }
+                members[classify(neighbors).1] += 1;
+            }
+            assert!(members.iter().all(|&n| n > 0), "count {} has an empty letter", count);
+        }
+    }
+
+    /// The count and Hensel letter of the neighbors of a configuration.
+    fn letter(index: usize) -> String {
+        let (count, letter) = classify(index);
+        format!("{}{}", count, &LETTERS[count][letter..letter + 1])
+    }
+
+    #[test]
+    fn test_letters() {
+        // The configurations of the letters told apart by how their corners
+        // lie, as drawn by Golly and LifeWiki.
+        assert_eq!(letter(NW | NE | W), "3n");
+        assert_eq!(letter(N | NE | SW), "3q");
+        assert_eq!(letter(NE | SE | S), "3n");
+        assert_eq!(letter(NW | N | NE | SE), "4n");
+        assert_eq!(letter(NW | N | W | SE), "4q");
+        assert_eq!(letter(NEIGHBORS & !(NW | NE | W)), "5n");
+        assert_eq!(letter(NEIGHBORS & !(N | NE | SW)), "5q");
+        assert_eq!(letter(NW | N | W), "3a");
+        assert_eq!(letter(NW | NE | S), "3y");
+        assert_eq!(letter(NW | N | NE | S), "4t");
+
+        // B3/S2-i34q: a live cell survives four neighbors only in the q
+        // configuration, and two unless they are opposite.
+        let rule: Rule = "B3/S2-i34q".parse().unwrap();
+        assert_eq!(rule.next(Cell::ALIVE, CENTER | NW | N | W | SE), Cell::ALIVE);
+        assert_eq!(rule.next(Cell::ALIVE, CENTER | NW | N | NE | SE), Cell::DEAD);
+        assert_eq!(rule.next(Cell::ALIVE, CENTER | N | S), Cell::DEAD);
+        assert_eq!(rule.next(Cell::ALIVE, CENTER | N | E), Cell::ALIVE);
+    }
+
+    #[test]
+    fn test_non_totalistic() {
+        let totalistic: Rule = "B3/S23".parse().unwrap();
+        let explicit: Rule = "B3cekainyqjr/S2cekain3cekainyqjr".parse().unwrap();
+        assert_eq!(totalistic, explicit);
+        assert_eq!(explicit.to_string(), "B3/S23");
+
+        let rule: Rule = "b2-a/s12".parse().unwrap();
+        assert_eq!(rule.to_string(), "B2-a/S12");
+        assert_eq!(rule, "B2ceikn/S12".parse::<Rule>().unwrap());
+        assert_eq!(rule.next(Cell::DEAD, N | NE), Cell::DEAD);
+        assert_eq!(rule.next(Cell::DEAD, N | W), Cell::ALIVE);
+        assert_eq!(rule.next(Cell::ALIVE, CENTER | SE), Cell::ALIVE);
+
+        let rule: Rule = "B3-jk4ir/S23-a".parse().unwrap();
+        assert_eq!(rule.to_string(), "B3-kj4ir/S23-a");
+    }
+
+    #[test]
+    fn test_non_totalistic_errors() {
+        assert!("B1k/S".parse::<Rule>().is_err());
+        assert!("B2x/S".parse::<Rule>().is_err());
+        assert!("B2-/S".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_generations() {
+        let brians_brain: Rule = "/2/3".parse().unwrap();
+        assert_eq!(brians_brain.to_string(), "B2/S/C3");
+        assert_eq!("B2/S/C3".parse::<Rule>().unwrap(), brians_brain);
+        assert_eq!(brians_brain.states(), 3);
+        assert_eq!(brians_brain.next(Cell::DEAD, N | S), Cell::ALIVE);
+        assert_eq!(brians_brain.next(Cell::ALIVE, CENTER | N | S), Cell::new(2));
+        assert_eq!(brians_brain.next(Cell::new(2), N | S), Cell::DEAD);
+
+        let star_wars: Rule = "345/2/4".parse().unwrap();
+        assert_eq!(star_wars.to_string(), "B2/S345/C4");
+        assert_eq!(star_wars.next(Cell::new(2), 0), Cell::new(3));
+        assert_eq!(star_wars.next(Cell::new(3), N | S), Cell::DEAD);
+        assert!((star_wars.shade(Cell::new(3)) - 1.0 / 3.0).abs() < 1e-6);
+
+        assert!("/2/1".parse::<Rule>().is_err());
+        assert!("B2/S/C256".parse::<Rule>().is_err());
+        assert!("B2/S/C3/C4".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_larger_than_life() {
+        let bosco: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(bosco.states(), 2);
+        assert_eq!(bosco.next_by_count(Cell::DEAD, 34), Cell::ALIVE);
+        assert_eq!(bosco.next_by_count(Cell::DEAD, 46), Cell::DEAD);
+        assert_eq!(bosco.next_by_count(Cell::ALIVE, 58), Cell::ALIVE);
+
+        let rule: Rule = "r3,c4,m0,s1..2,b2..2,nc".parse().unwrap();
+        assert_eq!(rule.to_string(), "R3,C4,M0,S1..2,B2..2,NC");
+        assert_eq!(rule.next_by_count(Cell::ALIVE, 3), Cell::new(2));
+    }
+}
@@ -0,0 +1,73 @@
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+use crate::image::{Image, Rgb};
+
+/// An image as an 8-bit RGB PNG file.
+pub fn encode(image: &Image) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut encoder = Encoder::new(&mut bytes, image.width, image.height);
+    encoder.set_color(ColorType::Rgb);
+    encoder.set_depth(BitDepth::Eight);
+    let data: Vec<u8> = image.pixels.iter().flatten().cloned().collect();
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&data))
+        .map_err(|error| format!("cannot write PNG: {}", error))?;
+    Ok(bytes)
+}
+
+/// The image in a PNG file of any color type and bit depth. Transparency is
+/// ignored.
+pub fn decode(bytes: &[u8]) -> Result<Image, String> {
+    let invalid = |error: png::DecodingError| format!("invalid PNG: {}", error);
+    let mut decoder = Decoder::new(bytes);
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(invalid)?;
+    let channels = info.color_type.samples();
+    let pixels: Vec<Rgb> = data[..info.buffer_size()]
+        .chunks(info.line_size)
+        .flat_map(|line| line[..info.width as usize * channels].chunks(channels))
+        .map(|pixel| match info.color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => [pixel[0]; 3],
+            _ => [pixel[0], pixel[1], pixel[2]],
+        })
+        .collect();
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![[0, 0, 0], [255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255], [1, 2, 3]],
+        };
+        let bytes = encode(&image).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(decode(&bytes).unwrap(), image);
+
+        // A grayscale image with an alpha channel and 16 bits per sample.
+        let mut bytes = Vec::new();
+        let mut encoder = Encoder::new(&mut bytes, 2, 1);
+        encoder.set_color(ColorType::GrayscaleAlpha);
+        encoder.set_depth(BitDepth::Sixteen);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[0x12, 0x34, 0xff, 0xff, 0xab, 0xcd, 0, 0])
+            .unwrap();
+        assert_eq!(decode(&bytes).unwrap().pixels, vec![[0x12; 3], [0xab; 3]]);
+
+        assert!(decode(b"GIF89a").is_err());
+    }
+}
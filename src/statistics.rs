@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+
+use crate::Cell;
+
+/// The population of a universe and how it changed, kept up to date as it
+/// is advanced and edited.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// The number of live cells, or `None` until they are counted again.
+    population: Option<u64>,
+    births: u32,
+    deaths: u32,
+    /// The population after each of the most recent steps, oldest first.
+    history: VecDeque<u32>,
+    history_length: usize,
+}
+
+impl Statistics {
+    pub fn new() -> Statistics {
+        Statistics::default()
+    }
+
+    pub fn population(&self) -> Option<u64> {
+        self.population
+    }
+
+    /// Forget the population, after the cells were replaced wholesale.
+    pub fn invalidate(&mut self) {
+        self.population = None;
+    }
+
+    /// Count a cell edited from `old` to `new`.
+    pub fn edit(&mut self, old: Cell, new: Cell) {
+        if let Some(population) = &mut self.population {
+            *population = *population + new.is_alive() as u64 - old.is_alive() as u64;
+        }
+    }
+
+    /// The number of cells that came alive in the last step.
+    pub fn births(&self) -> u32 {
+        self.births
+    }
+
+    /// The number of live cells that died or started dying in the last
+    /// step.
+    pub fn deaths(&self) -> u32 {
+        self.deaths
+    }
+
+    /// Record a step with `births` and `deaths` that left `population` live
+    /// cells.
+    pub fn record(&mut self, births: u32, deaths: u32, population: u64) {
+        self.births = births;
+        self.deaths = deaths;
+        self.population = Some(population);
+        if self.history_length > 0 {
+            if self.history.len() == self.history_length {
+                self.history.pop_front();
+            }
+            self.history.push_back(population.min(u32::MAX as u64) as u32);
+        }
+    }
+
+    /// Keep the population of the last `length` steps, or none for 0.
+    pub fn set_history_length(&mut self, length: usize) {
+        self.history_length = length;
+        while self.history.len() > length {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn history(&self) -> Vec<u32> {
+        self.history.iter().cloned().collect()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+}
+
+/// The number of live cells in `cells`.
+pub fn count(cells: &[Cell]) -> u64 {
+    cells.iter().filter(|cell| cell.is_alive()).count() as u64
+}
+
+/// The births and deaths from `old` to `new` cells.
+pub fn changes(old: &[Cell], new: &[Cell]) -> (u32, u32) {
+    old.iter()
+        .zip(new)
+        .fold((0, 0), |(births, deaths), (&old, &new)| match (old.is_alive(), new.is_alive()) {
+            (false, true) => (births + 1, deaths),
+            (true, false) => (births, deaths + 1),
+            _ => (births, deaths),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes() {
+        let (o, x, y) = (Cell::DEAD, Cell::ALIVE, Cell::new(2));
+        assert_eq!(changes(&[o, x, x, y, o], &[x, x, y, o, o]), (1, 1));
+        assert_eq!(count(&[o, x, x, y]), 2);
+    }
+
+    #[test]
+    fn test_history() {
+        let mut statistics = Statistics::new();
+        statistics.record(3, 0, 3);
+        assert_eq!(statistics.history(), Vec::<u32>::new());
+        statistics.set_history_length(3);
+        for population in 4..8 {
+            statistics.record(1, 0, population);
+        }
+        assert_eq!(statistics.history(), vec![5, 6, 7]);
+        statistics.set_history_length(2);
+        assert_eq!(statistics.history(), vec![6, 7]);
+
+        statistics.edit(Cell::DEAD, Cell::ALIVE);
+        statistics.edit(Cell::ALIVE, Cell::new(2));
+        statistics.edit(Cell::DEAD, Cell::ALIVE);
+        assert_eq!(statistics.population(), Some(8));
+        statistics.invalidate();
+        statistics.edit(Cell::DEAD, Cell::ALIVE);
+        assert_eq!(statistics.population(), None);
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt::Write;
+
+use crate::image::{Palette, Rgb};
+use crate::Cell;
+
+fn hex([red, green, blue]: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", red, green, blue)
+}
+
+/// Draw `width` by `height` cells as an SVG image with the same layout as
+/// `Image::render`: squares of `cell_px` units, separated and surrounded by
+/// grid lines one unit wide with `grid`.
+///
+/// The background has the dead color, and the cells of each other state
+/// are one path, with horizontal runs of cells merged into one rectangle.
+/// Grid lines are drawn over the cells.
+pub fn write(width: u32, height: u32, cells: &[Cell], cell_px: u32, grid: bool, palette: &Palette) -> String {
+    let line = grid as u32;
+    let pitch = cell_px + line;
+    let (image_width, image_height) = (width * pitch + line, height * pitch + line);
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" \
+         shape-rendering=\"crispEdges\">",
+        image_width, image_height
+    );
+    let _ = writeln!(
+        svg,
+        "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+        image_width,
+        image_height,
+        hex(palette.color(Cell::DEAD))
+    );
+
+    for state in 1..palette.states.len() {
+        let cell = Cell::new(state as u8);
+        let mut path = String::new();
+        for row in 0..height {
+            let cells = &cells[(row * width) as usize..((row + 1) * width) as usize];
+            let mut col = 0;
+            while col < width {
+                if cells[col as usize] != cell {
+                    col += 1;
+                    continue;
+                }
+                let run = cells[col as usize..].iter().take_while(|&&other| other == cell).count() as u32;
+                let _ = write!(
+                    path,
+                    "M{} {}h{}v{}h-{}z",
+                    col * pitch + line,
+                    row * pitch + line,
+                    run * pitch - line,
+                    cell_px,
+                    run * pitch - line
+                );
+                col += run;
+            }
+        }
+        if !path.is_empty() {
+            let _ = writeln!(svg, "<path fill=\"{}\" d=\"{}\"/>", hex(palette.color(cell)), path);
+        }
+    }
+
+    if grid {
+        let mut path = String::new();
+        for col in 0..=width {
+            let _ = write!(path, "M{}.5 0v{}", col * pitch, image_height);
+        }
+        for row in 0..=height {
+            let _ = write!(path, "M0 {}.5h{}", row * pitch, image_width);
+        }
+        let _ = writeln!(svg, "<path stroke=\"{}\" d=\"{}\"/>", hex(palette.grid), path);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+
+    #[test]
+    fn test_write() {
+        let palette = Palette::new(&"B2/S/C3".parse::<Rule>().unwrap(), &[0x000000, 0xffffff, 0xff0000]);
+        let (o, x, y) = (Cell::DEAD, Cell::ALIVE, Cell::new(2));
+        let cells = [x, x, o, x, o, y, y, y];
+        assert_eq!(
+            write(4, 2, &cells, 10, false, &palette),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"40\" height=\"20\" viewBox=\"0 0 40 20\" \
+             shape-rendering=\"crispEdges\">\n\
+             <rect width=\"40\" height=\"20\" fill=\"#000000\"/>\n\
+             <path fill=\"#ffffff\" d=\"M0 0h20v10h-20zM30 0h10v10h-10z\"/>\n\
+             <path fill=\"#808080\" d=\"M10 10h30v10h-30z\"/>\n\
+             </svg>\n"
+        );
+        assert_eq!(
+            write(2, 1, &[x, x], 2, true, &palette),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"7\" height=\"4\" viewBox=\"0 0 7 4\" \
+             shape-rendering=\"crispEdges\">\n\
+             <rect width=\"7\" height=\"4\" fill=\"#000000\"/>\n\
+             <path fill=\"#ffffff\" d=\"M1 1h5v2h-5z\"/>\n\
+             <path stroke=\"#ff0000\" d=\"M0.5 0v4M3.5 0v4M6.5 0v4M0 0.5h7M0 3.5h7\"/>\n\
+             </svg>\n"
+        );
+    }
+}
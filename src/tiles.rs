@@ -0,0 +1,160 @@
+use std::ops::Range;
+
+use crate::topology::Topology;
+use crate::Cell;
+
+/// The width and height of the square tiles changes are tracked in.
+pub const TILE: u32 = 32;
+
+/// Which tiles of a `width` by `height` universe hold a cell that changed
+/// in the last generation, or was edited since.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirtyTiles {
+    width: u32,
+    height: u32,
+    columns: u32,
+    rows: u32,
+    dirty: Vec<bool>,
+}
+
+impl DirtyTiles {
+    /// Track a `width` by `height` universe, with every tile dirty.
+    pub fn new(width: u32, height: u32) -> DirtyTiles {
+        let (columns, rows) = (width.div_ceil(TILE), height.div_ceil(TILE));
+        DirtyTiles {
+            width,
+            height,
+            columns,
+            rows,
+            dirty: vec![true; (columns * rows) as usize],
+        }
+    }
+
+    /// Track a `width` by `height` universe, with no tile dirty.
+    pub fn clean(width: u32, height: u32) -> DirtyTiles {
+        let mut tiles = DirtyTiles::new(width, height);
+        tiles.dirty.iter_mut().for_each(|dirty| *dirty = false);
+        tiles
+    }
+
+    /// The dirty tiles of the change from `old` to `new` cells, every tile
+    /// when their sizes differ.
+    pub fn diff(width: u32, height: u32, old: &[Cell], new: &[Cell]) -> DirtyTiles {
+        if old.len() != new.len() {
+            return DirtyTiles::new(width, height);
+        }
+        let mut tiles = DirtyTiles::clean(width, height);
+        for (idx, (old, new)) in old.iter().zip(new).enumerate() {
+            if old != new {
+                let (row, col) = (idx as u32 / width, idx as u32 % width);
+                tiles.mark(row, col);
+            }
+        }
+        tiles
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Mark the tile holding a cell as dirty.
+    pub fn mark(&mut self, row: u32, col: u32) {
+        self.dirty[((row / TILE) * self.columns + col / TILE) as usize] = true;
+    }
+
+    pub fn is_dirty(&self, tile_row: u32, tile_col: u32) -> bool {
+        self.dirty[(tile_row * self.columns + tile_col) as usize]
+    }
+
+    /// The rows and columns of cells in a tile.
+    pub fn cells(&self, tile_row: u32, tile_col: u32) -> (Range<u32>, Range<u32>) {
+        let rows = tile_row * TILE..((tile_row + 1) * TILE).min(self.height);
+        let cols = tile_col * TILE..((tile_col + 1) * TILE).min(self.width);
+        (rows, cols)
+    }
+
+    /// Which tiles may change in the next generation of a rule looking at
+    /// the eight nearest neighbors: the dirty tiles and their neighbors,
+    /// indexed row by row. Any other tile's neighborhood is the same as in
+    /// the last generation, so it comes out the same.
+    ///
+    /// Twisted topologies join edge tiles to tiles in other places, so any
+    /// dirty tile on the edge of such a universe makes every edge tile
+    /// active.
+    pub fn active(&self, topology: Topology) -> Vec<bool> {
+        let (rows, columns) = (self.rows as i64, self.columns as i64);
+        let wrap = topology == Topology::Torus;
+        let twisted = !matches!(topology, Topology::Torus | Topology::Plane | Topology::Unbounded);
+        let on_edge = |row: i64, col: i64| {
+            row == 0 || col == 0 || row == rows - 1 || col == columns - 1
+        };
+
+        let mut active = vec![false; self.dirty.len()];
+        let mut edge_dirty = false;
+        for row in 0..rows {
+            for col in 0..columns {
+                if !self.dirty[(row * columns + col) as usize] {
+                    continue;
+                }
+                edge_dirty |= on_edge(row, col);
+                for (dy, dx) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dy, dx))) {
+                    let (mut row, mut col) = (row + dy, col + dx);
+                    if wrap {
+                        row = row.rem_euclid(rows);
+                        col = col.rem_euclid(columns);
+                    } else if row < 0 || col < 0 || row >= rows || col >= columns {
+                        continue;
+                    }
+                    active[(row * columns + col) as usize] = true;
+                }
+            }
+        }
+        if twisted && edge_dirty {
+            for row in 0..rows {
+                for col in 0..columns {
+                    if on_edge(row, col) {
+                        active[(row * columns + col) as usize] = true;
+                    }
+                }
+            }
+        }
+        active
+    }
+
+    /// The row and column of every dirty tile, one after the other.
+    pub fn positions(&self) -> Vec<u32> {
+        (0..self.rows)
+            .flat_map(|row| (0..self.columns).map(move |col| (row, col)))
+            .filter(|&(row, col)| self.is_dirty(row, col))
+            .flat_map(|(row, col)| vec![row, col])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active() {
+        let mut tiles = DirtyTiles::new(100, 70);
+        assert_eq!(tiles.positions().len(), 2 * 12);
+        assert_eq!((tiles.columns(), tiles.rows()), (4, 3));
+        assert_eq!(tiles.cells(2, 3), (64..70, 96..100));
+
+        let cells = vec![Cell::DEAD; 100 * 70];
+        tiles = DirtyTiles::diff(100, 70, &cells, &cells);
+        assert!(tiles.positions().is_empty());
+        tiles.mark(0, 99);
+        assert_eq!(tiles.positions(), vec![0, 3]);
+
+        let count = |active: Vec<bool>| active.into_iter().filter(|&active| active).count();
+        assert_eq!(count(tiles.active(Topology::Plane)), 4);
+        assert_eq!(count(tiles.active(Topology::Torus)), 9);
+        assert_eq!(count(tiles.active(Topology::Sphere)), 11);
+    }
+}
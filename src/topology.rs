@@ -0,0 +1,272 @@
+use std::fmt;
+
+/// A pair of opposite edges of the universe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edges {
+    /// The top and bottom edges.
+    Horizontal,
+    /// The left and right edges.
+    Vertical,
+}
+
+/// How the edges of a universe are joined together.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Topology {
+    /// Nothing lies beyond the edges; cells outside are always dead.
+    Plane,
+    /// An infinite plane with no edges at all. The universe's width and
+    /// height only describe the viewport onto it.
+    Unbounded,
+    /// Opposite edges are joined, so patterns wrap around.
+    #[default]
+    Torus,
+    /// Opposite edges are joined, with the given pair joined with a twist so
+    /// that patterns crossing them come back mirrored.
+    KleinBottle(Edges),
+    /// Both pairs of opposite edges are joined with a twist.
+    CrossSurface,
+    /// The top edge is joined to the left edge and the bottom edge to the
+    /// right edge. Only square universes can be spheres.
+    Sphere,
+}
+
+/// Wrap `coord` into `0..size`, returning how many times it wrapped.
+fn wrap(coord: i64, size: u32) -> (i64, i64) {
+    let size = size as i64;
+    (coord.rem_euclid(size), coord.div_euclid(size))
+}
+
+fn parse_size(spec: &str, size: &str) -> Result<u32, String> {
+    if size.contains(['+', '-']) {
+        return Err(format!(
+            "invalid topology \"{}\": shifted edges are not supported",
+            spec
+        ));
+    }
+    match size.parse::<u32>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(format!(
+            "invalid topology \"{}\": expected a positive size, found \"{}\"",
+            spec, size
+        )),
+    }
+}
+
+impl Topology {
+    /// Parse a Golly style bounded grid specification such as `T64,64`,
+    /// `P64,64`, `K64*,64`, `C64,64` or `S64`, returning the topology with
+    /// the width and height of the grid. `P0,0`, a plane of zero size, is the
+    /// unbounded plane and has no width or height.
+    pub fn parse(spec: &str) -> Result<(Topology, u32, u32), String> {
+        let trimmed = spec.trim();
+        if trimmed.eq_ignore_ascii_case("p0,0") {
+            return Ok((Topology::Unbounded, 0, 0));
+        }
+        let mut chars = trimmed.chars();
+        let kind = chars.next().map(|c| c.to_ascii_uppercase());
+        let sizes: Vec<&str> = chars.as_str().split(',').map(str::trim).collect();
+
+        if kind == Some('S') {
+            let size = match sizes.as_slice() {
+                [size] => parse_size(trimmed, size)?,
+                [width, height] if width == height => parse_size(trimmed, width)?,
+                _ => {
+                    return Err(format!(
+                        "invalid topology \"{}\": a sphere must be square, e.g. \"S64\"",
+                        trimmed
+                    ))
+                }
+            };
+            return Ok((Topology::Sphere, size, size));
+        }
+
+        let (width, height) = match sizes.as_slice() {
+            [width, height] => (*width, *height),
+            _ => {
+                return Err(format!(
+                    "invalid topology \"{}\": expected a width and height, e.g. \"T64,64\"",
+                    trimmed
+                ))
+            }
+        };
+        let twisted_width = width.ends_with('*');
+        let twisted_height = height.ends_with('*');
+        let width = parse_size(trimmed, width.trim_end_matches('*'))?;
+        let height = parse_size(trimmed, height.trim_end_matches('*'))?;
+
+        let topology = match (kind, twisted_width, twisted_height) {
+            (Some('P'), false, false) => Topology::Plane,
+            (Some('T'), false, false) => Topology::Torus,
+            (Some('K'), true, false) => Topology::KleinBottle(Edges::Horizontal),
+            (Some('K'), false, true) => Topology::KleinBottle(Edges::Vertical),
+            (Some('K'), _, _) => {
+                return Err(format!(
+                    "invalid topology \"{}\": a Klein bottle needs exactly one twisted size marked with '*'",
+                    trimmed
+                ))
+            }
+            (Some('C'), false, false) => Topology::CrossSurface,
+            (Some('P'), _, _) | (Some('T'), _, _) | (Some('C'), _, _) => {
+                return Err(format!(
+                    "invalid topology \"{}\": only Klein bottles have twisted edges",
+                    trimmed
+                ))
+            }
+            _ => {
+                return Err(format!(
+                    "invalid topology \"{}\": expected it to start with P, T, K, C or S",
+                    trimmed
+                ))
+            }
+        };
+        Ok((topology, width, height))
+    }
+
+    /// Map a cell position that may lie outside of a `width` by `height`
+    /// universe onto the cell it refers to, or `None` when it refers to no
+    /// cell and should be treated as dead.
+    pub fn map(self, width: u32, height: u32, row: i64, col: i64) -> Option<(u32, u32)> {
+        let inside = |row: i64, col: i64| {
+            row >= 0 && row < height as i64 && col >= 0 && col < width as i64
+        };
+        if inside(row, col) {
+            return Some((row as u32, col as u32));
+        }
+
+        let (row, col) = match self {
+            Topology::Plane | Topology::Unbounded => return None,
+            Topology::Torus => (wrap(row, height).0, wrap(col, width).0),
+            Topology::KleinBottle(Edges::Horizontal) => {
+                let (row, crossings) = wrap(row, height);
+                let col = if crossings % 2 != 0 { width as i64 - 1 - col } else { col };
+                (row, wrap(col, width).0)
+            }
+            Topology::KleinBottle(Edges::Vertical) => {
+                let (col, crossings) = wrap(col, width);
+                let row = if crossings % 2 != 0 { height as i64 - 1 - row } else { row };
+                (wrap(row, height).0, col)
+            }
+            Topology::CrossSurface => {
+                let (wrapped_row, row_crossings) = wrap(row, height);
+                let (wrapped_col, col_crossings) = wrap(col, width);
+                let row = if col_crossings % 2 != 0 {
+                    height as i64 - 1 - wrapped_row
+                } else {
+                    wrapped_row
+                };
+                let col = if row_crossings % 2 != 0 {
+                    width as i64 - 1 - wrapped_col
+                } else {
+                    wrapped_col
+                };
+                (row, col)
+            }
+            Topology::Sphere => {
+                let size = height as i64;
+                match (row, col) {
+                    (row, col) if row < 0 && col >= 0 && col < size => (col, -row - 1),
+                    (row, col) if row >= size && col >= 0 && col < size => (col, 2 * size - 1 - row),
+                    (row, col) if col < 0 && row >= 0 && row < size => (-col - 1, row),
+                    (row, col) if col >= size && row >= 0 && row < size => (2 * size - 1 - col, row),
+                    // Positions beyond the corners of a sphere have no cell.
+                    _ => return None,
+                }
+            }
+        };
+        if inside(row, col) {
+            Some((row as u32, col as u32))
+        } else {
+            None
+        }
+    }
+
+    /// The Golly style specification of this topology for a `width` by
+    /// `height` universe.
+    pub fn spec(self, width: u32, height: u32) -> String {
+        match self {
+            Topology::Plane => format!("P{},{}", width, height),
+            Topology::Unbounded => String::from("P0,0"),
+            Topology::Torus => format!("T{},{}", width, height),
+            Topology::KleinBottle(Edges::Horizontal) => format!("K{}*,{}", width, height),
+            Topology::KleinBottle(Edges::Vertical) => format!("K{},{}*", width, height),
+            Topology::CrossSurface => format!("C{},{}", width, height),
+            Topology::Sphere => format!("S{}", width),
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Topology::Plane => "plane",
+            Topology::Unbounded => "unbounded plane",
+            Topology::Torus => "torus",
+            Topology::KleinBottle(_) => "Klein bottle",
+            Topology::CrossSurface => "cross-surface",
+            Topology::Sphere => "sphere",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Topology::parse("T64,32"), Ok((Topology::Torus, 64, 32)));
+        assert_eq!(Topology::parse("p10,20"), Ok((Topology::Plane, 10, 20)));
+        assert_eq!(Topology::parse("P0,0"), Ok((Topology::Unbounded, 0, 0)));
+        assert_eq!(
+            Topology::parse("K64*,64"),
+            Ok((Topology::KleinBottle(Edges::Horizontal), 64, 64))
+        );
+        assert_eq!(
+            Topology::parse("K64,48*"),
+            Ok((Topology::KleinBottle(Edges::Vertical), 64, 48))
+        );
+        assert_eq!(Topology::parse("C8,8"), Ok((Topology::CrossSurface, 8, 8)));
+        assert_eq!(Topology::parse("S16"), Ok((Topology::Sphere, 16, 16)));
+        assert_eq!(Topology::parse("S16,16"), Ok((Topology::Sphere, 16, 16)));
+
+        assert!(Topology::parse("K64,64").is_err());
+        assert!(Topology::parse("K64*,64*").is_err());
+        assert!(Topology::parse("T64*,64").is_err());
+        assert!(Topology::parse("S16,8").is_err());
+        assert!(Topology::parse("T64+2,64").is_err());
+        assert!(Topology::parse("T0,64").is_err());
+        assert!(Topology::parse("P0,64").is_err());
+        assert!(Topology::parse("X64,64").is_err());
+
+        for spec in &["P10,20", "T64,32", "K64*,64", "K64,48*", "C8,8", "S16"] {
+            let (topology, width, height) = Topology::parse(spec).unwrap();
+            assert_eq!(&topology.spec(width, height), spec);
+        }
+    }
+
+    #[test]
+    fn test_map() {
+        assert_eq!(Topology::Plane.map(4, 3, -1, 0), None);
+        assert_eq!(Topology::Plane.map(4, 3, 2, 3), Some((2, 3)));
+        assert_eq!(Topology::Torus.map(4, 3, -1, 4), Some((2, 0)));
+
+        let klein = Topology::KleinBottle(Edges::Horizontal);
+        assert_eq!(klein.map(4, 3, -1, 0), Some((2, 3)));
+        assert_eq!(klein.map(4, 3, 3, 1), Some((0, 2)));
+        assert_eq!(klein.map(4, 3, 1, 4), Some((1, 0)));
+
+        let klein = Topology::KleinBottle(Edges::Vertical);
+        assert_eq!(klein.map(4, 3, 0, -1), Some((2, 3)));
+        assert_eq!(klein.map(4, 3, -1, 1), Some((2, 1)));
+
+        assert_eq!(Topology::CrossSurface.map(4, 3, -1, 0), Some((2, 3)));
+        assert_eq!(Topology::CrossSurface.map(4, 3, 0, -1), Some((2, 3)));
+
+        assert_eq!(Topology::Sphere.map(4, 4, -1, 2), Some((2, 0)));
+        assert_eq!(Topology::Sphere.map(4, 4, 2, -1), Some((0, 2)));
+        assert_eq!(Topology::Sphere.map(4, 4, 4, 1), Some((1, 3)));
+        assert_eq!(Topology::Sphere.map(4, 4, 1, 4), Some((3, 1)));
+        assert_eq!(Topology::Sphere.map(4, 4, -1, -1), None);
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// One of the eight symmetries of the square, applied to a pattern around
+/// its bounding box. Rotations are clockwise as seen on the canvas, with
+/// rows going down and columns going right.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Transform {
+    #[default]
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirror left to right.
+    FlipHorizontal,
+    /// Mirror top to bottom.
+    FlipVertical,
+    /// Reflect across the diagonal from the top left corner, swapping rows
+    /// and columns.
+    Transpose,
+    /// Reflect across the diagonal from the top right corner.
+    AntiTranspose,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 8] = [
+        Transform::Identity,
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+        Transform::Transpose,
+        Transform::AntiTranspose,
+    ];
+
+    /// The transform as a map of rows and columns around the origin.
+    pub(crate) fn linear(self, (row, col): (i64, i64)) -> (i64, i64) {
+        match self {
+            Transform::Identity => (row, col),
+            Transform::Rotate90 => (col, -row),
+            Transform::Rotate180 => (-row, -col),
+            Transform::Rotate270 => (-col, row),
+            Transform::FlipHorizontal => (row, -col),
+            Transform::FlipVertical => (-row, col),
+            Transform::Transpose => (col, row),
+            Transform::AntiTranspose => (-col, -row),
+        }
+    }
+
+    /// `self` followed by `next`.
+    pub fn then(self, next: Transform) -> Transform {
+        // A cell off every axis and diagonal tells the transforms apart.
+        let cell = (1, 2);
+        let moved = next.linear(self.linear(cell));
+        Transform::ALL
+            .iter()
+            .cloned()
+            .find(|transform| transform.linear(cell) == moved)
+            .unwrap()
+    }
+
+    /// The transform that undoes `self`.
+    pub fn inverse(self) -> Transform {
+        match self {
+            Transform::Rotate90 => Transform::Rotate270,
+            Transform::Rotate270 => Transform::Rotate90,
+            transform => transform,
+        }
+    }
+
+    /// Whether the transform swaps rows and columns.
+    pub fn swaps_axes(self) -> bool {
+        self.linear((1, 0)).0 == 0
+    }
+
+    /// The height and width of a `height` by `width` pattern once
+    /// transformed.
+    pub fn size(self, height: u32, width: u32) -> (u32, u32) {
+        if self.swaps_axes() {
+            (width, height)
+        } else {
+            (height, width)
+        }
+    }
+
+    /// Where the cell at `row` and `col` of a `height` by `width` pattern
+    /// goes, keeping the pattern's top left corner in place.
+    pub fn apply(self, row: u32, col: u32, height: u32, width: u32) -> (u32, u32) {
+        let (row, col) = self.linear((row as i64, col as i64));
+        let (top, left) = self.linear((height as i64 - 1, width as i64 - 1));
+        ((row - top.min(0)) as u32, (col - left.min(0)) as u32)
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    /// A transform by name, or a sequence of them separated by spaces or
+    /// commas, applied from left to right.
+    fn from_str(names: &str) -> Result<Transform, String> {
+        let mut transform = Transform::Identity;
+        for name in names.split([' ', ',']).filter(|name| !name.is_empty()) {
+            let next = match name.to_ascii_lowercase().as_str() {
+                "identity" => Transform::Identity,
+                "rot90" => Transform::Rotate90,
+                "rot180" => Transform::Rotate180,
+                "rot270" => Transform::Rotate270,
+                "flip_h" => Transform::FlipHorizontal,
+                "flip_v" => Transform::FlipVertical,
+                "transpose" => Transform::Transpose,
+                "anti_transpose" => Transform::AntiTranspose,
+                _ => {
+                    return Err(format!(
+                        "unknown transform \"{}\", expected \"identity\", \"rot90\", \"rot180\", \"rot270\", \
+                         \"flip_h\", \"flip_v\", \"transpose\" or \"anti_transpose\"",
+                        name
+                    ))
+                }
+            };
+            transform = transform.then(next);
+        }
+        Ok(transform)
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Transform::Identity => "identity",
+            Transform::Rotate90 => "rot90",
+            Transform::Rotate180 => "rot180",
+            Transform::Rotate270 => "rot270",
+            Transform::FlipHorizontal => "flip_h",
+            Transform::FlipVertical => "flip_v",
+            Transform::Transpose => "transpose",
+            Transform::AntiTranspose => "anti_transpose",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: [&str; 3] = [
+        ".O.",
+        "..O",
+        "OOO",
+    ];
+
+    /// Transform a picture of a pattern.
+    fn transform<S: AsRef<str>>(picture: &[S], transform: Transform) -> Vec<String> {
+        let (height, width) = (picture.len() as u32, picture[0].as_ref().len() as u32);
+        let (new_height, new_width) = transform.size(height, width);
+        let mut rows = vec![vec!['.'; new_width as usize]; new_height as usize];
+        for (row, line) in picture.iter().enumerate() {
+            for (col, c) in line.as_ref().chars().enumerate() {
+                if c == 'O' {
+                    let (row, col) = transform.apply(row as u32, col as u32, height, width);
+                    rows[row as usize][col as usize] = 'O';
+                }
+            }
+        }
+        rows.into_iter().map(|row| row.into_iter().collect()).collect()
+    }
+
+    #[test]
+    fn test_glider() {
+        let expected = [
+            (Transform::Identity, [".O.", "..O", "OOO"]),
+            (Transform::Rotate90, ["O..", "O.O", "OO."]),
+            (Transform::Rotate180, ["OOO", "O..", ".O."]),
+            (Transform::Rotate270, [".OO", "O.O", "..O"]),
+            (Transform::FlipHorizontal, [".O.", "O..", "OOO"]),
+            (Transform::FlipVertical, ["OOO", "..O", ".O."]),
+            (Transform::Transpose, ["..O", "O.O", ".OO"]),
+            (Transform::AntiTranspose, ["OO.", "O.O", "O.."]),
+        ];
+        for (transformation, picture) in expected.iter() {
+            assert_eq!(transform(&GLIDER, *transformation), picture, "{}", transformation);
+        }
+    }
+
+    #[test]
+    fn test_size() {
+        let l = ["O.", "O.", "OO"];
+        assert_eq!(transform(&l, Transform::Rotate90), ["OOO", "O.."]);
+        assert_eq!(transform(&l, Transform::FlipVertical), ["OO", "O.", "O."]);
+        assert_eq!(transform(&l, Transform::AntiTranspose), ["O..", "OOO"]);
+    }
+
+    #[test]
+    fn test_composition() {
+        use Transform::*;
+        assert_eq!(Rotate90.then(Rotate90), Rotate180);
+        assert_eq!(Rotate90.then(Rotate180), Rotate270);
+        assert_eq!(Rotate270.then(Rotate90), Identity);
+        assert_eq!(FlipHorizontal.then(FlipVertical), Rotate180);
+        assert_eq!(Rotate90.then(FlipHorizontal), Transpose);
+        assert_eq!(FlipHorizontal.then(Rotate90), AntiTranspose);
+        for &a in Transform::ALL.iter() {
+            assert_eq!(a.then(a.inverse()), Identity);
+            for &b in Transform::ALL.iter() {
+                assert_eq!(
+                    transform(&transform(&GLIDER, a), b),
+                    transform(&GLIDER, a.then(b)),
+                    "{} then {}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        for &transform in Transform::ALL.iter() {
+            assert_eq!(transform.to_string().parse::<Transform>().unwrap(), transform);
+        }
+        assert_eq!("rot90 flip_h".parse::<Transform>().unwrap(), Transform::Transpose);
+        assert_eq!("ROT90,rot90".parse::<Transform>().unwrap(), Transform::Rotate180);
+        assert_eq!("".parse::<Transform>().unwrap(), Transform::Identity);
+        assert!("rot45".parse::<Transform>().is_err());
+    }
+}
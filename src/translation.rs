@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::object;
+use crate::Cell;
+
+/// How the contents of a universe recur: after how many generations, and
+/// how far they moved.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Translation {
+    period: u32,
+    dy: i64,
+    dx: i64,
+}
+
+#[wasm_bindgen]
+impl Translation {
+    /// The number of generations until the contents first look the same.
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    /// How many rows the contents moved down every period.
+    pub fn dy(&self) -> i32 {
+        self.dy as i32
+    }
+
+    /// How many columns the contents moved right every period.
+    pub fn dx(&self) -> i32 {
+        self.dx as i32
+    }
+
+    /// The speed and direction in c notation, such as `2c/5 orthogonal`,
+    /// or `None` when the contents recur in place.
+    pub fn speed(&self) -> Option<String> {
+        object::velocity(self.period, self.dy, self.dx)
+    }
+}
+
+impl Translation {
+    pub fn new(period: u32, dy: i64, dx: i64) -> Translation {
+        Translation { period, dy, dx }
+    }
+}
+
+/// The shift of rows and columns that moves the cells of `before` onto
+/// those of `after`, both sorted, or `None` when there is none. On a
+/// `height` by `width` torus the shift wraps around its edges, and the one
+/// closest to no shift is returned.
+pub fn shift(before: &[(i64, i64, Cell)], after: &[(i64, i64, Cell)], torus: Option<(u32, u32)>) -> Option<(i64, i64)> {
+    if before.len() != after.len() {
+        return None;
+    }
+    let &(first_row, first_col, first_cell) = before.first()?;
+    let wrap = |row: i64, col: i64| match torus {
+        Some((height, width)) => (row.rem_euclid(height as i64), col.rem_euclid(width as i64)),
+        None => (row, col),
+    };
+    // A shift to the range of half the torus either way.
+    let centered = |shift: i64, size: u32| {
+        let size = size as i64;
+        if shift > size / 2 {
+            shift - size
+        } else {
+            shift
+        }
+    };
+    let cells: HashMap<(i64, i64), Cell> = after.iter().map(|&(row, col, cell)| ((row, col), cell)).collect();
+    after
+        .iter()
+        .filter(|&&(_, _, cell)| cell == first_cell)
+        .map(|&(row, col, _)| {
+            let (dy, dx) = wrap(row - first_row, col - first_col);
+            match torus {
+                Some((height, width)) => (centered(dy, height), centered(dx, width)),
+                None => (dy, dx),
+            }
+        })
+        .filter(|&(dy, dx)| {
+            before
+                .iter()
+                .all(|&(row, col, cell)| cells.get(&wrap(row + dy, col + dx)) == Some(&cell))
+        })
+        .min_by_key(|&(dy, dx)| (dy.abs() + dx.abs(), dy, dx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(positions: &[(i64, i64)]) -> Vec<(i64, i64, Cell)> {
+        let mut cells: Vec<(i64, i64, Cell)> = positions.iter().map(|&(row, col)| (row, col, Cell::ALIVE)).collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    #[test]
+    fn test_shift() {
+        let glider = cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let moved = cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        assert_eq!(shift(&glider, &moved, None), Some((1, 1)));
+        assert_eq!(shift(&moved, &glider, None), Some((-1, -1)));
+        assert_eq!(shift(&glider, &glider, None), Some((0, 0)));
+        assert_eq!(shift(&glider, &moved[1..], None), None);
+
+        // The glider moved across the bottom right corner of an 8 by 8
+        // torus.
+        let wrapped = cells(&[(7, 0), (0, 1), (1, 7), (1, 0), (1, 1)]);
+        let corner = cells(&[(6, 7), (7, 0), (0, 6), (0, 7), (0, 0)]);
+        assert_eq!(shift(&corner, &wrapped, Some((8, 8))), Some((1, 1)));
+        assert_eq!(shift(&corner, &wrapped, None), None);
+
+        // A row around the torus matches itself with any shift along it.
+        let row = cells(&[(2, 0), (2, 1), (2, 2), (2, 3)]);
+        assert_eq!(shift(&row, &row, Some((4, 4))), Some((0, 0)));
+    }
+}
@@ -24,6 +24,7 @@ function main() {
     const hFlip = document.getElementById("h-flip");
     const vFlip = document.getElementById("v-flip");
     const invert = document.getElementById("invert");
+    const pasteMode = document.getElementById("paste-mode");
     const hFlipLabel = document.getElementById("hFlipLabel");
     const vFlipLabel = document.getElementById("vFlipLabel");
     const invertLabel = document.getElementById("invertLabel");
@@ -37,11 +38,19 @@ function main() {
     let speedUpFactor = 1;
     let speedDownCounter = 1;
 
+    for (const name of universe.list_patterns()) {
+        const option = document.createElement("option");
+        option.value = name;
+        option.textContent = universe.pattern_info(name).title();
+        insertPopulation.appendChild(option);
+    }
+
     gridActionToggle.checked = true;
     insertPopulation.disabled = true;
     hFlip.disabled = true;
     vFlip.disabled = true;
     invert.disabled = true;
+    pasteMode.disabled = true;
     hFlipLabel.style.color = "#aaa";
     vFlipLabel.style.color = "#aaa";
     invertLabel.style.color = "#aaa";
@@ -77,7 +86,7 @@ function main() {
         if (gridActionToggle.checked) {
             universe.toggle_cell(row, col);
         } else {
-            universe.seed_population(row, col, insertPopulation, hFlip, vFlip, invert);
+            universe.seed_population(row, col, insertPopulation, hFlip, vFlip, invert, pasteMode.value);
         }
 
         universe.render()
@@ -211,6 +220,7 @@ function main() {
         hFlip.disabled = false;
         vFlip.disabled = false;
         invert.disabled = false;
+        pasteMode.disabled = false;
         hFlipLabel.style.color = "#000";
         vFlipLabel.style.color = "#000";
         invertLabel.style.color = "#000";
@@ -222,11 +232,32 @@ function main() {
         hFlip.disabled = true;
         vFlip.disabled = true;
         invert.disabled = true;
+        pasteMode.disabled = true;
         hFlipLabel.style.color = "#aaa";
         vFlipLabel.style.color = "#aaa";
         invertLabel.style.color = "#aaa";
     });
 
+    document.addEventListener("keydown", event => {
+        if (!(event.ctrlKey || event.metaKey) || event.key.toLowerCase() !== "z") {
+            return;
+        }
+        event.preventDefault();
+        if (event.shiftKey) {
+            universe.redo();
+        } else {
+            universe.undo();
+        }
+        // Undoing a resize changes the size of the universe.
+        width = universe.width();
+        height = universe.height();
+        cellSize = universe.size();
+        hSizeSet.valueAsNumber = width;
+        vSizeSet.valueAsNumber = height;
+        cellSizeSet.valueAsNumber = cellSize;
+        universe.render();
+    });
+
 
     universe.render();
 